@@ -0,0 +1,166 @@
+//! [actix-multipart] interop.
+//!
+//! [`Part`]s convert to actix-multipart's [`TestFormField`]s, and a list of parts can be turned
+//! into a [`Multipart`] stream for exercising actix handlers. In the other direction,
+//! [`from_field`] buffers an incoming [`Field`] into a [`Part`].
+//!
+//! [actix-multipart]: https://docs.rs/actix-multipart
+
+use crate::{FormData, Part};
+use actix_multipart::test::TestFormField;
+use actix_multipart::{Field, Multipart, MultipartError};
+use actix_web::error::PayloadError;
+use actix_web::http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use actix_web::web::Bytes;
+use std::fmt;
+
+impl<'a> TryFrom<&'a Part<'_>> for TestFormField<'a> {
+    type Error = std::io::Error;
+
+    /// Converts a part, reading its file if it has one.
+    fn try_from(part: &'a Part<'_>) -> std::io::Result<Self> {
+        let body = match part.file_path() {
+            Some(path) => std::fs::read(path)?,
+            None => part.body().unwrap_or_default().to_vec(),
+        };
+        let mut field = TestFormField::new(part.name(), body);
+        if let Some(filename) = part.filename() {
+            field = field.filename(filename);
+        }
+        if let Some(content_type) = part.content_type() {
+            let content_type = content_type
+                .parse()
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?;
+            field = field.content_type(content_type);
+        }
+        Ok(field)
+    }
+}
+
+/// Writes `parts` to a document and returns it as a [`Multipart`] stream, as an actix handler
+/// would receive it.
+///
+/// # Errors
+///
+/// Returns an error if a part is read from a file that cannot be opened.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`FormData::new`].
+pub fn multipart(parts: &[Part<'_>]) -> std::io::Result<Multipart> {
+    let mut form = FormData::new(Vec::new());
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_str(&form.content_type_header())
+            .expect("boundary should be a valid header value"),
+    );
+    let result = parts.iter().try_for_each(|part| form.write_part(part));
+    let document = form.finish_or_abort(result)?;
+    Ok(Multipart::new(
+        &headers,
+        futures_util::stream::once(std::future::ready(Ok::<_, PayloadError>(Bytes::from(
+            document,
+        )))),
+    ))
+}
+
+/// Buffers `field` into a [`Part`], reading at most `limit` bytes of body.
+///
+/// # Errors
+///
+/// Returns an error if the field has no name, if its body is longer than `limit`, or if reading
+/// it fails.
+pub async fn from_field(field: &mut Field, limit: usize) -> Result<Part<'static>, FieldError> {
+    let name = field.name().ok_or(FieldError::MissingName)?.to_owned();
+    let filename = field
+        .content_disposition()
+        .and_then(|disposition| disposition.get_filename())
+        .map(str::to_owned);
+    let content_type = field.content_type().map(ToString::to_string);
+    let body = field
+        .bytes(limit)
+        .await
+        .map_err(|_| FieldError::LimitExceeded)?
+        .map_err(FieldError::Multipart)?;
+
+    let part = match content_type {
+        Some(content_type) => Part::file(name, Vec::from(body), content_type),
+        None => Part::text(
+            name,
+            String::from_utf8(body.into()).map_err(|_| FieldError::InvalidText)?,
+        ),
+    };
+    Ok(match filename {
+        Some(filename) => part.with_filename(filename),
+        None => part,
+    })
+}
+
+/// An error from [`from_field`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FieldError {
+    /// The field has no `name` parameter.
+    MissingName,
+    /// The field's body is longer than the limit.
+    LimitExceeded,
+    /// The field has no `Content-Type` and its body is not valid UTF-8.
+    InvalidText,
+    /// Reading the field failed.
+    Multipart(MultipartError),
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldError::MissingName => f.write_str("field has no name"),
+            FieldError::LimitExceeded => f.write_str("field body exceeds the limit"),
+            FieldError::InvalidText => f.write_str("text field is not valid UTF-8"),
+            FieldError::Multipart(err) => write!(f, "failed to read field: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FieldError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FieldError::Multipart(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Part;
+    use futures_util::StreamExt;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        // the whole document is buffered, so nothing ever waits
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let parts = [
+            Part::text("cute", "yes"),
+            Part::file("corro", &b"<svg/>"[..], "image/svg+xml").with_filename("corro.svg"),
+        ];
+        let mut multipart = super::multipart(&parts).unwrap();
+        for part in &parts {
+            let mut field = block_on(multipart.next()).unwrap().unwrap();
+            assert_eq!(
+                &block_on(super::from_field(&mut field, 1024)).unwrap(),
+                part
+            );
+        }
+        assert!(block_on(multipart.next()).is_none());
+    }
+}
@@ -0,0 +1,94 @@
+//! [`Arbitrary`] support, for property testing and fuzzing with random but valid forms.
+//!
+//! A `Vec<Part>` from [`Arbitrary`] is a random form that this crate can always write:
+//!
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use form_data_builder::{FormData, Part};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let seed = [0x5a; 256];
+//! let parts = Vec::<Part>::arbitrary(&mut Unstructured::new(&seed))?;
+//!
+//! let mut form = FormData::new(Vec::new());
+//! for part in &parts {
+//!     form.write_part(part)?;
+//! }
+//! let document = form.finish()?;
+//! // ... feed `document` to the parser under test ...
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::Part;
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+const CONTENT_TYPES: &[&str] = &[
+    "application/octet-stream",
+    "application/json",
+    "image/png",
+    "image/svg+xml",
+    "text/plain",
+    "text/plain; charset=utf-8",
+];
+
+impl<'a> Arbitrary<'a> for Part<'static> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let name = parameter(u)?;
+        if u.arbitrary()? {
+            let body: Vec<u8> = u.arbitrary()?;
+            let part = Part::file(name, body, *u.choose(CONTENT_TYPES)?);
+            Ok(if u.arbitrary()? {
+                part.with_filename(parameter(u)?)
+            } else {
+                part
+            })
+        } else {
+            Ok(Part::text(name, String::arbitrary(u)?))
+        }
+    }
+}
+
+/// Generates a non-empty value that can be written as a quoted `Content-Disposition` parameter.
+fn parameter(u: &mut Unstructured<'_>) -> Result<String> {
+    let s: String = String::arbitrary(u)?
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"' && *c != '\\')
+        .collect();
+    Ok(if s.is_empty() { "field".to_owned() } else { s })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{verify, FormDataParts};
+    use crate::{FormData, Part};
+    use arbitrary::{Arbitrary, Unstructured};
+
+    #[test]
+    fn round_trip() {
+        for seed in 0..64_u8 {
+            let data: Vec<u8> = (0..=u8::MAX)
+                .cycle()
+                .take(1024)
+                .map(|i| i.wrapping_mul(seed).wrapping_add(seed))
+                .collect();
+            let parts = Vec::<Part>::arbitrary(&mut Unstructured::new(&data)).unwrap();
+
+            let mut form = FormData::new(Vec::new());
+            let boundary = form.boundary().to_owned();
+            for part in &parts {
+                form.write_part(part).unwrap();
+            }
+            let document = form.finish().unwrap();
+
+            verify(&document, &boundary).unwrap();
+            let parsed = FormDataParts::parse(&document, &boundary).unwrap();
+            assert_eq!(parsed.len(), parts.len());
+            for (parsed, part) in parsed.iter().zip(&parts) {
+                assert_eq!(parsed.name(), part.name());
+                assert_eq!(parsed.filename(), part.filename());
+                assert_eq!(Some(parsed.body()), part.body());
+            }
+        }
+    }
+}
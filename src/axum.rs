@@ -0,0 +1,138 @@
+//! [axum] integration.
+//!
+//! [`FormDataParts`] is an extractor: it buffers the request body, takes the boundary from the
+//! request's `Content-Type` header, and parses the document.
+//!
+//! ```
+//! use form_data_builder::parse::FormDataParts;
+//!
+//! async fn upload(parts: FormDataParts) -> String {
+//!     format!("received {} parts", parts.len())
+//! }
+//! ```
+//!
+//! The request body size limit is axum's usual `DefaultBodyLimit`.
+//!
+//! [axum]: https://docs.rs/axum
+
+use crate::parse::FormDataParts;
+use axum_core::extract::rejection::BytesRejection;
+use axum_core::extract::{FromRequest, Request};
+use axum_core::response::{IntoResponse, Response};
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::StatusCode;
+use std::fmt;
+
+impl<S: Send + Sync> FromRequest<S> for FormDataParts {
+    type Rejection = FormDataRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let boundary = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(crate::parse::boundary_from_content_type)
+            .ok_or(FormDataRejection::InvalidContentType)?;
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(FormDataRejection::Body)?;
+        FormDataParts::parse(&body, &boundary).map_err(FormDataRejection::Malformed)
+    }
+}
+
+/// Rejection for the [`FormDataParts`] extractor.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FormDataRejection {
+    /// The request has no `multipart/form-data` `Content-Type` with a boundary.
+    InvalidContentType,
+    /// The request body could not be read.
+    Body(BytesRejection),
+    /// The request body is not a well-formed document.
+    Malformed(std::io::Error),
+}
+
+impl fmt::Display for FormDataRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormDataRejection::InvalidContentType => {
+                f.write_str("request is not multipart/form-data with a boundary")
+            }
+            FormDataRejection::Body(err) => write!(f, "failed to read request body: {err}"),
+            FormDataRejection::Malformed(err) => write!(f, "malformed multipart/form-data: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FormDataRejection {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FormDataRejection::InvalidContentType => None,
+            FormDataRejection::Body(err) => Some(err),
+            FormDataRejection::Malformed(err) => Some(err),
+        }
+    }
+}
+
+impl IntoResponse for FormDataRejection {
+    fn into_response(self) -> Response {
+        match self {
+            FormDataRejection::InvalidContentType => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, self.to_string()).into_response()
+            }
+            FormDataRejection::Body(err) => err.into_response(),
+            FormDataRejection::Malformed(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormDataRejection;
+    use crate::parse::FormDataParts;
+    use crate::FormData;
+    use axum_core::body::Body;
+    use axum_core::extract::{FromRequest, Request};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    fn extract(request: Request) -> Result<FormDataParts, FormDataRejection> {
+        // a fully-buffered body is ready on the first poll
+        let mut future = pin!(FormDataParts::from_request(request, &()));
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(result) => result,
+            Poll::Pending => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn extractor() {
+        let mut form = FormData::new(Vec::new());
+        let content_type = form.content_type_header_value();
+        form.write_field("cute", "yes").unwrap();
+        let document = form.finish().unwrap();
+
+        let request = Request::builder()
+            .header("content-type", content_type)
+            .body(Body::from(document.clone()))
+            .unwrap();
+        let parts = extract(request).unwrap();
+        assert_eq!(parts.get("cute").unwrap().body(), b"yes");
+
+        let request = Request::builder()
+            .header("content-type", "text/plain")
+            .body(Body::from(document))
+            .unwrap();
+        assert!(matches!(
+            extract(request),
+            Err(FormDataRejection::InvalidContentType)
+        ));
+    }
+}
@@ -0,0 +1,337 @@
+#[cfg(feature = "stream")]
+use crate::FormDataStream;
+use crate::{parse, FormData, FormDataError, FormDataOptions, FormDataReader, Part};
+use std::io::{Result, Write};
+
+/// Collects a document's settings and parts, and checks all of them at once before anything is
+/// written. Created with [`FormData::builder`].
+///
+/// [`FormData`] checks each part as it is written, so a mistake in the last part is only found
+/// after the others have been sent. A builder finds every mistake up front, and reports all of
+/// them in one [`FormDataError::Invalid`] error:
+///
+/// ```
+/// use form_data_builder::{FormData, FormDataError, Part};
+///
+/// let err = FormData::builder()
+///     .boundary("boundary")
+///     .max_parts(2)
+///     .part(Part::text("cute", "yes"))
+///     .part(Part::file("corro", &b"<svg/>"[..], "svg"))
+///     .part(Part::path("ferris", "testdata/missing.png", "image/png"))
+///     .emit(Vec::new())
+///     .unwrap_err();
+/// match err.get_ref().and_then(|err| err.downcast_ref()) {
+///     Some(FormDataError::Invalid { problems }) => assert_eq!(problems.len(), 3),
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct FormDataBuilder<'a> {
+    boundary: Option<String>,
+    options: FormDataOptions,
+    parts: Vec<Part<'a>>,
+    max_parts: Option<usize>,
+    max_len: Option<u64>,
+}
+
+impl FormData<()> {
+    /// Starts building a document whose settings and parts are all checked before it is written.
+    /// See [`FormDataBuilder`].
+    pub fn builder<'a>() -> FormDataBuilder<'a> {
+        FormDataBuilder::default()
+    }
+}
+
+impl<'a> FormDataBuilder<'a> {
+    /// Uses a fixed boundary, like [`FormData::with_boundary`], instead of generating one.
+    pub fn boundary(mut self, boundary: &str) -> FormDataBuilder<'a> {
+        self.boundary = Some(boundary.to_owned());
+        self
+    }
+
+    /// Uses `options` for writing the document.
+    pub fn options(mut self, options: FormDataOptions) -> FormDataBuilder<'a> {
+        self.options = options;
+        self
+    }
+
+    /// Adds a part to the end of the document.
+    pub fn part(mut self, part: Part<'a>) -> FormDataBuilder<'a> {
+        self.parts.push(part);
+        self
+    }
+
+    /// Adds parts to the end of the document.
+    pub fn parts<I: IntoIterator<Item = Part<'a>>>(mut self, parts: I) -> FormDataBuilder<'a> {
+        self.parts.extend(parts);
+        self
+    }
+
+    /// Rejects documents with more than `max` parts.
+    pub fn max_parts(mut self, max: usize) -> FormDataBuilder<'a> {
+        self.max_parts = Some(max);
+        self
+    }
+
+    /// Rejects documents longer than `max` bytes, going by the sizes of files when the document is
+    /// checked.
+    ///
+    /// Under a [`DuplicatePolicy`][crate::DuplicatePolicy] other than `Keep`, this reads each file
+    /// to find the duplicates that would be skipped or written empty.
+    pub fn max_len(mut self, max: u64) -> FormDataBuilder<'a> {
+        self.max_len = Some(max);
+        self
+    }
+
+    /// Checks the document without writing it.
+    ///
+    /// Part names must be non-empty and can't contain `"` or line breaks, and neither can
+    /// filenames. With [`FormDataOptions::strict_names`], names are also checked as described for
+    /// [`FormData::set_strict_names`]. Content types must look like `type/subtype`, optionally
+    /// followed by parameters. Files must exist and not be directories, and the boundary and
+    /// limits must be respected.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FormDataError::Invalid`] error, of kind
+    /// [`ErrorKind::InvalidInput`][std::io::ErrorKind::InvalidInput], listing every problem.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        if let Some(boundary) = &self.boundary {
+            if !parse::is_valid_boundary(boundary) {
+                problems.push(format!("invalid boundary {boundary:?}"));
+            }
+        }
+        if let Some(max) = self.max_parts {
+            if self.parts.len() > max {
+                problems.push(format!("{} parts is more than {max}", self.parts.len()));
+            }
+        }
+        for part in &self.parts {
+            let name = part.name();
+            if name.is_empty()
+                || has_unquotable(name)
+                || (self.options.strict_names && crate::check_name(name).is_err())
+            {
+                problems.push(format!("invalid part name {name:?}"));
+            }
+            if let Some(filename) = part.filename().filter(|filename| has_unquotable(filename)) {
+                problems.push(format!("part {name:?}: invalid filename {filename:?}"));
+            }
+            if let Some(content_type) = part.content_type() {
+                if !is_valid_content_type(content_type) {
+                    problems.push(format!(
+                        "part {name:?}: invalid content type {content_type:?}"
+                    ));
+                }
+            }
+            if let Some(path) = part.file_path() {
+                match std::fs::metadata(path) {
+                    Ok(metadata) if metadata.is_dir() => {
+                        problems.push(format!("part {name:?}: {} is a directory", path.display()));
+                    }
+                    Ok(_) => {}
+                    Err(err) => problems.push(format!("part {name:?}: {}: {err}", path.display())),
+                }
+            }
+        }
+        if let Some(max) = self.max_len {
+            let mut form = match &self.boundary {
+                Some(boundary) => FormData::with_boundary(Vec::new(), boundary)
+                    .unwrap_or_else(|_| FormData::new(Vec::new())),
+                None => FormData::new(Vec::new()),
+            };
+            form.set_options(self.options.clone());
+            let len = form.predicted_len(&self.parts);
+            if len > max {
+                problems.push(format!("{len} bytes is more than {max}"));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(FormDataError::Invalid { problems }.into())
+        }
+    }
+
+    /// Checks the document, then writes its parts to `writer`, returning the unfinished
+    /// [`FormData`] so that more can be written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document fails [`FormDataBuilder::validate`], in which case nothing
+    /// is written, or if writing a part fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`] if no boundary was given.
+    pub fn build<W: Write>(self, writer: W) -> Result<FormData<W>> {
+        let (mut form, parts) = self.start(writer)?;
+        for part in &parts {
+            if let Err(err) = form.write_part(part) {
+                form.abort();
+                return Err(err);
+            }
+        }
+        Ok(form)
+    }
+
+    /// Like [`FormDataBuilder::build`], but also finishes the document, returning the writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormDataBuilder::build`], or if finishing
+    /// the document fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`] if no boundary was given.
+    pub fn emit<W: Write>(self, writer: W) -> Result<W> {
+        self.build(writer)?.finish()
+    }
+
+    /// Checks the document, then returns a reader that writes each part as the document is read
+    /// up to it, instead of writing the whole document up front. See [`FormDataReader`].
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use form_data_builder::{FormData, Part};
+    /// use std::io::Read;
+    ///
+    /// let mut reader = FormData::builder()
+    ///     .part(Part::text("cute", "yes"))
+    ///     .part(Part::path("corro", "testdata/corro.svg", "image/svg+xml"))
+    ///     .into_reader()?;
+    /// let content_type = reader.content_type_header();
+    /// let mut body = Vec::new();
+    /// reader.read_to_end(&mut body)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document fails [`FormDataBuilder::validate`].
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`] if no boundary was given.
+    pub fn into_reader(self) -> Result<FormDataReader<'a>> {
+        let (form, parts) = self.start(Vec::new())?;
+        Ok(FormDataReader::new(form, parts))
+    }
+
+    /// Like [`FormDataBuilder::into_reader`], but returns the document as a stream of chunks. See
+    /// [`FormDataStream`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document fails [`FormDataBuilder::validate`].
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`] if no boundary was given.
+    #[cfg(feature = "stream")]
+    pub fn into_stream(self) -> Result<FormDataStream<'a>> {
+        self.into_reader().map(FormDataStream::new)
+    }
+
+    /// Checks the document and sets up a [`FormData`] for writing its parts to `writer`.
+    fn start<W: Write>(self, writer: W) -> Result<(FormData<W>, Vec<Part<'a>>)> {
+        self.validate()?;
+        let mut form = match &self.boundary {
+            Some(boundary) => FormData::with_boundary(writer, boundary)?,
+            None => FormData::new(writer),
+        };
+        form.set_options(self.options);
+        Ok((form, self.parts))
+    }
+}
+
+/// Whether `s` can't go in a quoted header parameter as it is.
+fn has_unquotable(s: &str) -> bool {
+    s.contains(['"', '\r', '\n'])
+}
+
+fn is_valid_content_type(content_type: &str) -> bool {
+    let (essence, params) = content_type.split_once(';').unwrap_or((content_type, ""));
+    let valid_essence = essence
+        .trim_end()
+        .split_once('/')
+        .is_some_and(|(kind, subtype)| parse::is_token(kind) && parse::is_token(subtype));
+    valid_essence && !params.contains(|c: char| c.is_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FormData, FormDataError, Part};
+
+    #[test]
+    fn validate() {
+        let err = FormData::builder()
+            .boundary("bad\"boundary")
+            .max_parts(1)
+            .max_len(10)
+            .part(Part::text("", "yes"))
+            .part(
+                Part::file(
+                    "corro",
+                    &b"<svg/>"[..],
+                    "image/svg+xml; charset=\"utf-8\"\r\n",
+                )
+                .with_filename("corro\".svg"),
+            )
+            .part(Part::path("dir", "testdata", "application/octet-stream"))
+            .validate()
+            .unwrap_err();
+        let Some(FormDataError::Invalid { problems }) =
+            err.get_ref().and_then(|err| err.downcast_ref())
+        else {
+            unreachable!();
+        };
+        assert_eq!(problems.len(), 7, "{problems:?}");
+
+        let parts = [
+            Part::text("cute", "yes"),
+            Part::path(
+                "corro",
+                "testdata/corro.svg",
+                "image/svg+xml; charset=utf-8",
+            ),
+        ];
+        let document = FormData::builder()
+            .boundary("boundary")
+            .max_parts(2)
+            .max_len(10_000)
+            .parts(parts.iter().cloned())
+            .emit(Vec::new())
+            .unwrap();
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        for part in &parts {
+            expected.write_part(part).unwrap();
+        }
+        assert_eq!(document, expected.finish().unwrap());
+    }
+
+    #[test]
+    fn max_len_exact() {
+        let parts = [
+            Part::text("cute", "yes"),
+            Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+        ];
+        let len = FormData::builder()
+            .boundary("boundary")
+            .parts(parts.iter().cloned())
+            .emit(Vec::new())
+            .unwrap()
+            .len() as u64;
+        let builder = FormData::builder()
+            .boundary("boundary")
+            .parts(parts.iter().cloned());
+        builder.clone().max_len(len).validate().unwrap();
+        builder.max_len(len - 1).validate().unwrap_err();
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{FormData, FormDataOptions, Part};
+use bytes::BytesMut;
+use std::io::{Error, Result};
+use tokio_util::codec::Encoder;
+
+/// A [tokio-util] [`Encoder`] that encodes [`Part`]s into a `multipart/form-data` document,
+/// formatted exactly as [`FormData`] writes them.
+///
+/// Each part is encoded on its own, so a document can be sent through a framed transport as its
+/// parts become available. Call [`PartEncoder::encode_end`] after the last part to close the
+/// document.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use bytes::BytesMut;
+/// use form_data_builder::{Part, PartEncoder};
+/// use tokio_util::codec::Encoder;
+///
+/// let mut encoder = PartEncoder::new();
+/// let content_type = encoder.content_type_header();
+/// let mut buf = BytesMut::new();
+/// encoder.encode(Part::text("cute", "yes"), &mut buf)?;
+/// encoder.encode(&Part::path("corro", "testdata/corro.svg", "image/svg+xml"), &mut buf)?;
+/// encoder.encode_end(&mut buf)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [tokio-util]: https://docs.rs/tokio-util
+#[derive(Debug)]
+pub struct PartEncoder {
+    form: FormData<Vec<u8>>,
+}
+
+impl PartEncoder {
+    /// Creates an encoder with a generated boundary, like [`FormData::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`].
+    #[must_use]
+    pub fn new() -> PartEncoder {
+        PartEncoder {
+            form: FormData::new(Vec::new()),
+        }
+    }
+
+    /// Creates an encoder with `options` and a generated boundary, like
+    /// [`FormData::with_options`].
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`].
+    #[must_use]
+    pub fn with_options(options: FormDataOptions) -> PartEncoder {
+        PartEncoder {
+            form: FormData::with_options(Vec::new(), options),
+        }
+    }
+
+    /// Creates an encoder with a fixed boundary, like [`FormData::with_boundary`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormData::with_boundary`].
+    pub fn with_boundary(boundary: &str) -> Result<PartEncoder> {
+        Ok(PartEncoder {
+            form: FormData::with_boundary(Vec::new(), boundary)?,
+        })
+    }
+
+    /// Returns the boundary that separates the parts of the document.
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        self.form.boundary()
+    }
+
+    /// Returns the value of the `Content-Type` header for the document, like
+    /// [`FormData::content_type_header`].
+    #[must_use]
+    pub fn content_type_header(&self) -> String {
+        self.form.content_type_header()
+    }
+
+    /// Encodes the closing delimiter of the document into `dst`. Nothing can be encoded after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document was already closed.
+    pub fn encode_end(&mut self, dst: &mut BytesMut) -> Result<()> {
+        let buf = self.form.finish()?;
+        dst.extend_from_slice(&buf);
+        Ok(())
+    }
+
+    fn encode_part(&mut self, part: &Part<'_>, dst: &mut BytesMut) -> Result<()> {
+        let result = self.form.write_part(part);
+        let buf =
+            self.form.writer.as_mut().ok_or_else(|| {
+                Error::other("this method cannot be used after using `encode_end()`")
+            })?;
+        // pass on whatever was written, even if the part failed partway through, so that `dst`
+        // holds the same bytes a `FormData` would have written
+        dst.extend_from_slice(buf);
+        buf.clear();
+        result
+    }
+}
+
+impl Default for PartEncoder {
+    fn default() -> PartEncoder {
+        PartEncoder::new()
+    }
+}
+
+impl Encoder<Part<'_>> for PartEncoder {
+    type Error = Error;
+
+    fn encode(&mut self, item: Part<'_>, dst: &mut BytesMut) -> Result<()> {
+        self.encode_part(&item, dst)
+    }
+}
+
+impl Encoder<&Part<'_>> for PartEncoder {
+    type Error = Error;
+
+    fn encode(&mut self, item: &Part<'_>, dst: &mut BytesMut) -> Result<()> {
+        self.encode_part(item, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PartEncoder;
+    use crate::{FormData, Part};
+    use bytes::BytesMut;
+    use tokio_util::codec::Encoder;
+
+    #[test]
+    fn matches_form_data() {
+        let parts = [
+            Part::text("cute", "yes"),
+            Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+        ];
+        let mut encoder = PartEncoder::with_boundary("boundary").unwrap();
+        let mut buf = BytesMut::new();
+        for part in &parts {
+            encoder.encode(part, &mut buf).unwrap();
+        }
+        encoder.encode_end(&mut buf).unwrap();
+        assert!(encoder.encode(Part::text("late", "no"), &mut buf).is_err());
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        for part in &parts {
+            form.write_part(part).unwrap();
+        }
+        assert_eq!(buf, form.finish().unwrap());
+    }
+}
@@ -0,0 +1,21 @@
+use md5::{digest::Output, Md5};
+
+/// The `Content-MD5` header value ([RFC 1864][rfc1864]) for a body with hash `md5`.
+///
+/// [rfc1864]: https://www.rfc-editor.org/rfc/rfc1864.html
+pub(crate) fn content_md5(md5: &Output<Md5>) -> String {
+    base64::encode(md5)
+}
+
+#[cfg(test)]
+mod tests {
+    use md5::{Digest, Md5};
+
+    #[test]
+    fn content_md5() {
+        assert_eq!(
+            super::content_md5(&Md5::digest(b"yes")),
+            "phBcCmEbQbCPEglQY1Anng=="
+        );
+    }
+}
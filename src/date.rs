@@ -0,0 +1,63 @@
+use std::io::Write;
+use std::time::SystemTime;
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Appends `time` as an [RFC 822] date-time in GMT, such as `Wed, 12 Feb 1997 16:29:51 GMT`, as
+/// used by `Content-Disposition` date parameters.
+///
+/// [RFC 822]: https://www.rfc-editor.org/rfc/rfc822.html#section-5
+pub(crate) fn write_rfc822(out: &mut Vec<u8>, time: SystemTime) {
+    let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+        Err(before) => i64::try_from(before.duration().as_secs()).map_or(i64::MIN, |secs| -secs),
+    };
+    let days = secs.div_euclid(86400);
+    let secs = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let weekday = WEEKDAYS[usize::try_from((days + 4).rem_euclid(7)).unwrap_or_default()];
+    let _ = write!(
+        out,
+        "{weekday}, {day:02} {} {year:04} {:02}:{:02}:{:02} GMT",
+        MONTHS[usize::try_from(month - 1).unwrap_or_default()],
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    );
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date in the proleptic Gregorian
+/// calendar, using Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn write_rfc822() {
+        for (secs, expected) in [
+            (0, "Thu, 01 Jan 1970 00:00:00 GMT"),
+            (855_764_991, "Wed, 12 Feb 1997 16:29:51 GMT"),
+            (951_782_400, "Tue, 29 Feb 2000 00:00:00 GMT"),
+        ] {
+            let mut out = Vec::new();
+            super::write_rfc822(&mut out, SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,581 @@
+//! Deserializing parsed documents into Rust types with [serde].
+//!
+//! Each field of the target struct is matched by name against the parts of the document. Text
+//! parts deserialize into strings and anything that can be parsed from one (numbers, `bool`s,
+//! unit enum variants). File parts deserialize into bytes (`Vec<u8>`, or `serde_bytes`), or into
+//! a struct with any of the fields `filename`, `content_type`, and `body` (see [`FilePart`]).
+//! Repeated names deserialize into sequences.
+//!
+//! To keep files on disk rather than in memory, save them first with
+//! [`FormDataParts::save_to_dir`] and deserialize with [`from_saved`]. File parts then also
+//! deserialize into paths (`PathBuf`, or `String`) naming where each file was saved. A name sent
+//! with a single file part is still a sequence of bytes, so `Vec<PathBuf>` only works for names
+//! sent with more than one.
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use form_data_builder::{de::FilePart, parse::FormDataParts, FormData};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Upload {
+//!     cute: bool,
+//!     tags: Vec<String>,
+//!     corro: FilePart,
+//!     comment: Option<String>,
+//! }
+//!
+//! let mut form = FormData::new(Vec::new());
+//! let content_type = form.content_type_header();
+//! form.write_field("cute", "true")?;
+//! form.write_field("tags", "crab")?;
+//! form.write_field("tags", "unsafe")?;
+//! form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+//! let document = form.finish()?;
+//!
+//! let parts = FormDataParts::parse_with_content_type(&document, &content_type)?;
+//! let upload: Upload = form_data_builder::de::from_parts(&parts)?;
+//! assert!(upload.cute);
+//! assert_eq!(upload.tags, ["crab", "unsafe"]);
+//! assert_eq!(upload.corro.filename.as_deref(), Some("corro.svg"));
+//! assert_eq!(upload.comment, None);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [serde]: https://serde.rs
+
+use crate::parse::{FormDataParts, ParsedPart, SavedForm};
+use serde::de::value::{BorrowedStrDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Deserializes an instance of `T` from the parts of a document.
+///
+/// # Errors
+///
+/// Returns an error if the parts do not match the shape of `T`, or if a value cannot be parsed
+/// as the type of its field.
+pub fn from_parts<'de, T: Deserialize<'de>>(parts: &'de FormDataParts) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(parts))
+}
+
+/// Like [`from_parts`], but file parts can also be deserialized into the paths they were saved
+/// to, where `saved` is what [`FormDataParts::save_to_dir`] returned for `parts`.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::parse::{FormDataParts, SaveLimits};
+/// use serde::Deserialize;
+/// use std::path::PathBuf;
+///
+/// #[derive(Deserialize)]
+/// struct Upload {
+///     cute: bool,
+///     corro: PathBuf,
+/// }
+///
+/// # let dir = std::env::temp_dir().join(format!("fdb-doc-from-saved-{}", std::process::id()));
+/// # std::fs::create_dir_all(&dir)?;
+/// # let mut form = form_data_builder::FormData::new(Vec::new());
+/// # let boundary = form.boundary().to_owned();
+/// # form.write_field("cute", "true")?;
+/// # form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+/// # let document = form.finish()?;
+/// let parts = FormDataParts::parse(&document, &boundary)?;
+/// let saved = parts.save_to_dir(&dir, &SaveLimits::default())?;
+/// let upload: Upload = form_data_builder::de::from_saved(&parts, &saved)?;
+/// assert!(upload.cute);
+/// assert_eq!(upload.corro, dir.join("corro.svg"));
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error under the same conditions as [`from_parts`], if a saved path is not valid
+/// UTF-8, or if `saved` doesn't list the same file parts as `parts`.
+pub fn from_saved<'de, T: Deserialize<'de>>(
+    parts: &'de FormDataParts,
+    saved: &'de SavedForm,
+) -> Result<T, Error> {
+    T::deserialize(Deserializer::with_saved(parts, saved))
+}
+
+/// A file part, as deserialized from a document.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FilePart {
+    /// The `filename` parameter, if present.
+    pub filename: Option<String>,
+    /// The part's `Content-Type`, if present.
+    pub content_type: Option<String>,
+    /// The part's body.
+    #[serde(with = "body_bytes")]
+    pub body: Vec<u8>,
+    /// Where the part was saved, if deserialized with [`from_saved`].
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+/// A serde [`Deserializer`][de::Deserializer] over the parts of a document.
+///
+/// Most callers want [`from_parts`] instead.
+#[derive(Debug, Clone, Copy)]
+pub struct Deserializer<'de> {
+    parts: &'de FormDataParts,
+    saved: Option<&'de SavedForm>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer over `parts`.
+    #[must_use]
+    pub fn new(parts: &'de FormDataParts) -> Deserializer<'de> {
+        Deserializer { parts, saved: None }
+    }
+
+    /// Creates a deserializer over `parts` whose files were saved as `saved`. See [`from_saved`].
+    #[must_use]
+    pub fn with_saved(parts: &'de FormDataParts, saved: &'de SavedForm) -> Deserializer<'de> {
+        Deserializer {
+            parts,
+            saved: Some(saved),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut saved = self.saved.map(|saved| saved.files.iter());
+        // group parts by name, keeping the order in which each name first appeared
+        let mut fields: Vec<(&'de str, Vec<Sent<'de>>)> = Vec::new();
+        for part in self.parts {
+            let path = match &mut saved {
+                Some(files) if part.is_file() => match files.next() {
+                    Some(file) if file.name == part.name() => Some(file.path.as_path()),
+                    _ => return Err(Error("saved files don't match the document".to_owned())),
+                },
+                _ => None,
+            };
+            let sent = Sent { part, path };
+            match fields.iter_mut().find(|(name, _)| *name == part.name()) {
+                Some((_, parts)) => parts.push(sent),
+                None => fields.push((part.name(), vec![sent])),
+            }
+        }
+        if saved.is_some_and(|mut files| files.next().is_some()) {
+            return Err(Error("saved files don't match the document".to_owned()));
+        }
+        visitor.visit_map(FieldAccess {
+            fields: fields.into_iter(),
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// A part of the document, with where it was saved if it is a saved file.
+#[derive(Clone, Copy)]
+struct Sent<'de> {
+    part: &'de ParsedPart,
+    path: Option<&'de Path>,
+}
+
+struct FieldAccess<'de> {
+    fields: std::vec::IntoIter<(&'de str, Vec<Sent<'de>>)>,
+    value: Option<Vec<Sent<'de>>>,
+}
+
+impl<'de> MapAccess<'de> for FieldAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.fields.next() {
+            Some((name, parts)) => {
+                self.value = Some(parts);
+                seed.deserialize(BorrowedStrDeserializer::new(name))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let parts = self
+            .value
+            .take()
+            .ok_or_else(|| Error("value requested before key".to_owned()))?;
+        seed.deserialize(ValueDeserializer { parts })
+    }
+}
+
+/// Deserializes the value of one field, which may have been sent more than once.
+struct ValueDeserializer<'de> {
+    parts: Vec<Sent<'de>>,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    fn single(&self) -> Result<Sent<'de>, Error> {
+        match self.parts[..] {
+            [sent] => Ok(sent),
+            _ => Err(Error(format!(
+                "field {:?} was sent {} times, expected once",
+                self.parts[0].part.name(),
+                self.parts.len()
+            ))),
+        }
+    }
+
+    /// The part's value, or for a saved file, where it was saved.
+    fn text(&self) -> Result<&'de str, Error> {
+        let Sent { part, path } = self.single()?;
+        match path {
+            Some(path) => path.to_str().ok_or_else(|| {
+                Error(format!(
+                    "file {:?} was saved to a path that is not valid UTF-8",
+                    part.name()
+                ))
+            }),
+            None => std::str::from_utf8(part.body())
+                .map_err(|_| Error(format!("field {:?} is not valid UTF-8", part.name()))),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self) -> Result<T, Error>
+    where
+        T::Err: fmt::Display,
+    {
+        let text = self.text()?;
+        text.parse().map_err(|err| {
+            Error(format!(
+                "field {:?} has invalid value {text:?}: {err}",
+                self.parts[0].part.name()
+            ))
+        })
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.parts.len() > 1 {
+            return self.deserialize_seq(visitor);
+        }
+        let part = self.single()?.part;
+        match std::str::from_utf8(part.body()) {
+            Ok(text) if !part.is_file() => visitor.visit_borrowed_str(text),
+            _ => visitor.visit_borrowed_bytes(part.body()),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.text()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_bytes(self.single()?.part.body())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.parts[..] {
+            // a lone file part is a sequence of bytes, for `Vec<u8>`
+            [Sent { part, .. }] if part.is_file() => {
+                visitor.visit_seq(SeqDeserializer::new(part.body().iter().copied()))
+            }
+            _ => visitor.visit_seq(SeqDeserializer::new(
+                self.parts
+                    .into_iter()
+                    .map(|sent| ValueDeserializer { parts: vec![sent] }),
+            )),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let Sent { part, path } = self.single()?;
+        let mut entries = Vec::with_capacity(4);
+        if let Some(filename) = part.filename() {
+            entries.push(("filename", Entry::Str(filename)));
+        }
+        if let Some(content_type) = part.content_type() {
+            entries.push(("content_type", Entry::Str(content_type)));
+        }
+        entries.push(("body", Entry::Bytes(part.body())));
+        if path.is_some() {
+            entries.push(("path", Entry::Str(self.text()?)));
+        }
+        visitor.visit_map(de::value::MapDeserializer::new(entries.into_iter()))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.text()?.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        unit unit_struct tuple tuple_struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// A value in the map a file part deserializes as.
+enum Entry<'de> {
+    Str(&'de str),
+    Bytes(&'de [u8]),
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Entry<'de> {
+    type Deserializer = EntryDeserializer<'de>;
+
+    fn into_deserializer(self) -> EntryDeserializer<'de> {
+        EntryDeserializer(self)
+    }
+}
+
+struct EntryDeserializer<'de>(Entry<'de>);
+
+impl<'de> de::Deserializer<'de> for EntryDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Entry::Str(value) => visitor.visit_borrowed_str(value),
+            Entry::Bytes(value) => visitor.visit_borrowed_bytes(value),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Entry::Str(value) => BorrowedStrDeserializer::new(value).deserialize_seq(visitor),
+            Entry::Bytes(value) => visitor.visit_seq(SeqDeserializer::new(value.iter().copied())),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// `#[serde(with)]` helper so [`FilePart::body`] takes bytes without going through a sequence.
+mod body_bytes {
+    use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+    use std::fmt;
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("bytes")
+            }
+
+            fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Vec<u8>, E> {
+                Ok(v.as_bytes().to_vec())
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+                let mut out = Vec::new();
+                while let Some(byte) = seq.next_element()? {
+                    out.push(byte);
+                }
+                Ok(out)
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+/// An error encountered while deserializing a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::FormDataParts;
+    use serde::Deserialize;
+
+    const FIREFOX: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testdata/form-data.bin"
+    ));
+    const BOUNDARY: &str = "---------------------------20598614689265574691413388431";
+
+    #[test]
+    fn firefox() {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "kebab-case")]
+        struct Form<'a> {
+            file_a: Vec<u8>,
+            text_a: &'a str,
+            #[serde(with = "super::body_bytes")]
+            file_b: Vec<u8>,
+        }
+
+        let parts = FormDataParts::parse(FIREFOX, BOUNDARY).unwrap();
+        let form: Form<'_> = super::from_parts(&parts).unwrap();
+        assert_eq!(
+            form.file_a,
+            include_bytes!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/testdata/rustacean-flat-noshadow.png"
+            ))
+        );
+        assert!(form.text_a.starts_with("The Rust programming language"));
+        assert!(form.file_b.starts_with(b"<?xml"));
+    }
+
+    #[test]
+    fn bad_value() {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "kebab-case")]
+        struct Form {
+            #[allow(dead_code)]
+            text_a: u32,
+        }
+
+        let parts = FormDataParts::parse(FIREFOX, BOUNDARY).unwrap();
+        let err = super::from_parts::<Form>(&parts).unwrap_err();
+        assert!(err.to_string().contains("\"text-a\""));
+    }
+
+    #[test]
+    fn saved() {
+        use crate::parse::SaveLimits;
+        use std::path::PathBuf;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "kebab-case")]
+        struct Form {
+            file_a: PathBuf,
+            file_b: super::FilePart,
+            text_a: String,
+        }
+
+        let dir = std::env::temp_dir().join(format!("fdb-test-de-saved-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let parts = FormDataParts::parse(FIREFOX, BOUNDARY).unwrap();
+        let saved = parts.save_to_dir(&dir, &SaveLimits::default()).unwrap();
+        let form: Form = super::from_saved(&parts, &saved).unwrap();
+        assert_eq!(form.file_a, saved.files[0].path);
+        assert_eq!(
+            std::fs::read(&form.file_a).unwrap(),
+            parts.get("file-a").unwrap().body()
+        );
+        assert_eq!(form.file_b.path.as_ref(), Some(&saved.files[1].path));
+        assert!(form.file_b.body.starts_with(b"<?xml"));
+        assert!(form.text_a.starts_with("The Rust programming language"));
+
+        let mut mismatched = saved.clone();
+        mismatched.files.pop();
+        assert!(super::from_saved::<Form>(&parts, &mismatched).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
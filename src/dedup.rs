@@ -0,0 +1,45 @@
+/// What [`FormData::write_part`][crate::FormData::write_part] does with a file part whose body
+/// is the same as one already written to the document. Set with
+/// [`FormData::set_duplicate_policy`][crate::FormData::set_duplicate_policy].
+///
+/// Bodies are compared by their SHA-256 hashes, so under any policy but `Keep` each file is read
+/// twice: once to hash it, and once to write it. Non-file fields are always written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DuplicatePolicy {
+    /// Write every part.
+    #[default]
+    Keep,
+    /// Write nothing for a duplicate part. Skipped parts aren't counted as parts of the document.
+    Skip,
+    /// Add a `Content-ID` header made from the body's hash to every file part, and write
+    /// duplicates with an empty body, so that the server can find each body under the ID it was
+    /// first sent with.
+    ContentId,
+}
+
+/// The `Content-ID` header value for a body with hash `digest`.
+pub(crate) fn content_id(digest: &[u8; 32]) -> String {
+    use std::fmt::Write;
+
+    let mut id = String::from("<");
+    for byte in digest {
+        let _ = write!(id, "{byte:02x}");
+    }
+    id.push_str("@form-data-builder>");
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn content_id() {
+        let digest = Sha256::digest(b"yes").into();
+        assert_eq!(
+            super::content_id(&digest),
+            "<8a798890fe93817163b10b5f7bd2ca4d25d84c52739a645a889c173eee7d9d3d@form-data-builder>"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Details of why writing a document failed, carried inside the [`std::io::Error`]s this crate
+/// returns. Errors while writing a part say which part it was.
+///
+/// Errors that come with these details have the same [`ErrorKind`][io::ErrorKind] as the
+/// underlying error, so they can be handled as plain I/O errors, and the details can be recovered
+/// with [`std::io::Error::get_ref`]:
+///
+/// ```
+/// use form_data_builder::{FormData, FormDataError};
+/// use std::io::{Error, ErrorKind, Read};
+///
+/// struct Broken;
+///
+/// impl Read for Broken {
+///     fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+///         Err(Error::new(ErrorKind::ConnectionReset, "the disk fell off"))
+///     }
+/// }
+///
+/// let mut form = FormData::new(Vec::new());
+/// let err = form.write_file("corro", Broken, None, "image/svg+xml").unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+/// match err.get_ref().and_then(|err| err.downcast_ref()) {
+///     Some(FormDataError::PartBody { name, offset, .. }) => {
+///         assert_eq!((name.as_str(), *offset), ("corro", 0));
+///     }
+///     _ => unreachable!(),
+/// }
+/// # form.abort();
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FormDataError {
+    /// Reading the body of a part failed.
+    PartBody {
+        /// The name of the part.
+        name: String,
+        /// How many bytes of the body were read before the failure.
+        offset: u64,
+        /// The error from the reader.
+        source: io::Error,
+    },
+    /// Writing a part failed, such as because the writer failed or the document was cancelled.
+    Part {
+        /// The name of the part.
+        name: String,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A file could not be used as the body of a part, such as because it is a directory or it
+    /// can't be read. Nothing was written for the part.
+    Path {
+        /// The name of the part.
+        name: String,
+        /// The path of the file.
+        path: PathBuf,
+        /// Why the file could not be used.
+        source: io::Error,
+    },
+    /// Writing one of the files passed to [`FormData::write_files`][crate::FormData::write_files]
+    /// failed. The files before it were written.
+    File {
+        /// The name of the parts.
+        name: String,
+        /// The position of the file in the list, starting at 0.
+        index: usize,
+        /// The path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A part's name was rejected by
+    /// [`FormData::set_strict_names`][crate::FormData::set_strict_names]. Nothing was written for
+    /// the part.
+    InvalidName {
+        /// The rejected name.
+        name: String,
+        /// What is wrong with it.
+        reason: &'static str,
+    },
+    /// A document failed validation before anything was written. See
+    /// [`FormDataBuilder`][crate::FormDataBuilder].
+    Invalid {
+        /// Everything that is wrong with the document, one problem per entry.
+        problems: Vec<String>,
+    },
+}
+
+impl FormDataError {
+    /// Wraps `err`, which happened while writing the part called `name`, unless it already says
+    /// which part it came from.
+    pub(crate) fn in_part(name: &str, err: io::Error) -> io::Error {
+        if err
+            .get_ref()
+            .is_some_and(<dyn std::error::Error + Send + Sync>::is::<FormDataError>)
+        {
+            return err;
+        }
+        FormDataError::Part {
+            name: name.to_owned(),
+            source: err,
+        }
+        .into()
+    }
+}
+
+impl fmt::Display for FormDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // the source is included because these are usually displayed as `io::Error`s, which
+            // don't show their source
+            FormDataError::PartBody {
+                name,
+                offset,
+                source,
+            } => write!(
+                f,
+                "reading part {name:?} failed after {offset} bytes: {source}"
+            ),
+            FormDataError::Part { name, source } => {
+                write!(f, "writing part {name:?} failed: {source}")
+            }
+            FormDataError::Path { name, path, source } => write!(
+                f,
+                "part {name:?}: failed to open {}: {source}",
+                path.display()
+            ),
+            FormDataError::File {
+                name,
+                index,
+                path,
+                source,
+            } => write!(
+                f,
+                "file {index} ({}) of part {name:?}: {source}",
+                path.display()
+            ),
+            FormDataError::InvalidName { name, reason } => {
+                write!(f, "invalid part name {name:?}: {reason}")
+            }
+            FormDataError::Invalid { problems } => {
+                write!(f, "invalid document: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormDataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FormDataError::PartBody { source, .. }
+            | FormDataError::Part { source, .. }
+            | FormDataError::Path { source, .. }
+            | FormDataError::File { source, .. } => Some(source),
+            FormDataError::InvalidName { .. } | FormDataError::Invalid { .. } => None,
+        }
+    }
+}
+
+impl From<FormDataError> for io::Error {
+    fn from(err: FormDataError) -> io::Error {
+        let kind = match &err {
+            FormDataError::PartBody { source, .. }
+            | FormDataError::Part { source, .. }
+            | FormDataError::Path { source, .. }
+            | FormDataError::File { source, .. } => source.kind(),
+            FormDataError::InvalidName { .. } | FormDataError::Invalid { .. } => {
+                io::ErrorKind::InvalidInput
+            }
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// An error from a method that wraps a builder's writer, such as
+/// [`FormData::gzip`][crate::FormData::gzip], that hands back the builder it was called on,
+/// unchanged, so that its writer isn't lost.
+#[cfg(any(feature = "gzip", feature = "hmac"))]
+pub struct WrapError<W> {
+    error: io::Error,
+    form: Box<crate::FormData<W>>,
+}
+
+#[cfg(any(feature = "gzip", feature = "hmac"))]
+impl<W> WrapError<W> {
+    pub(crate) fn new(message: &str, form: crate::FormData<W>) -> WrapError<W> {
+        WrapError {
+            error: io::Error::other(message),
+            form: Box::new(form),
+        }
+    }
+
+    /// The error.
+    #[must_use]
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns the builder the method was called on.
+    #[must_use]
+    pub fn into_inner(self) -> crate::FormData<W> {
+        *self.form
+    }
+
+    /// Returns the error and the builder the method was called on.
+    #[must_use]
+    pub fn into_parts(self) -> (io::Error, crate::FormData<W>) {
+        (self.error, *self.form)
+    }
+}
+
+/// Leaves out the builder, like [`std::io::IntoInnerError`].
+#[cfg(any(feature = "gzip", feature = "hmac"))]
+impl<W> fmt::Debug for WrapError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "hmac"))]
+impl<W> fmt::Display for WrapError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "hmac"))]
+impl<W> std::error::Error for WrapError<W> {}
+
+#[cfg(any(feature = "gzip", feature = "hmac"))]
+impl<W> From<WrapError<W>> for io::Error {
+    fn from(err: WrapError<W>) -> io::Error {
+        err.error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormDataError;
+    use crate::tests::Broken;
+    use crate::FormData;
+    use std::io::{Error, ErrorKind, Read};
+
+    #[test]
+    fn part_body() {
+        let reader = b"yes".chain(Broken);
+        let mut form = FormData::new(Vec::new());
+        let err = form
+            .write_file("corro", reader, None, "image/svg+xml")
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert_eq!(
+            err.to_string(),
+            "reading part \"corro\" failed after 3 bytes: the disk fell off"
+        );
+        assert!(matches!(
+            err.get_ref().and_then(|err| err.downcast_ref()),
+            Some(FormDataError::PartBody { offset: 3, .. })
+        ));
+        form.abort();
+
+        let mut form = FormData::new(Failing);
+        let err = form.write_field_reader("cute", &b"yes"[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        assert_eq!(err.to_string(), "writing part \"cute\" failed: pipe");
+        form.abort();
+    }
+
+    struct Failing;
+
+    impl std::io::Write for Failing {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(Error::new(ErrorKind::BrokenPipe, "pipe"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::fs::{File, Metadata};
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+/// How [`FormData::write_path`][crate::FormData::write_path] and parts read from files treat
+/// paths that aren't regular files, such as symlinks, FIFOs, and device files. Set with
+/// [`FormData::set_file_type_policy`][crate::FormData::set_file_type_policy].
+///
+/// Opening a FIFO waits for something to write to it, and reading a device file can go on
+/// forever, so code that uploads paths it doesn't control should not follow them blindly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileTypePolicy {
+    /// Open whatever is at the path, following symlinks.
+    #[default]
+    Follow,
+    /// Fail with an error of kind [`ErrorKind::InvalidInput`] unless the path is a regular file
+    /// and not a symlink.
+    Reject,
+    /// Write nothing, and succeed, unless the path is a regular file and not a symlink. Parts
+    /// left out this way still count towards [`Progress::parts`][crate::Progress::parts].
+    Skip,
+}
+
+impl FileTypePolicy {
+    /// Opens the file at `path`, or returns `None` if it should be skipped.
+    pub(crate) fn open(self, path: &Path) -> Result<Option<File>> {
+        if self == FileTypePolicy::Follow {
+            let file = File::open(path)?;
+            // opening a directory succeeds on some platforms, and only reading it fails
+            if file.metadata()?.is_dir() {
+                return Err(Error::new(ErrorKind::InvalidInput, "is a directory"));
+            }
+            return Ok(Some(file));
+        }
+        let kind = if let Some(kind) = special_kind(&std::fs::symlink_metadata(path)?) {
+            kind
+        } else {
+            let file = File::open(path)?;
+            // the path may have been replaced since it was checked
+            match special_kind(&file.metadata()?) {
+                Some(kind) => kind,
+                None => return Ok(Some(file)),
+            }
+        };
+        if self == FileTypePolicy::Skip {
+            debug!("skipped {}: {}", path.display(), kind);
+            return Ok(None);
+        }
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("not a regular file ({kind})"),
+        ))
+    }
+}
+
+/// Describes what kind of file `metadata` is for, unless it is a regular file.
+fn special_kind(metadata: &Metadata) -> Option<&'static str> {
+    let file_type = metadata.file_type();
+    if file_type.is_file() {
+        return None;
+    }
+    if file_type.is_symlink() {
+        return Some("a symlink");
+    }
+    if file_type.is_dir() {
+        return Some("a directory");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        if file_type.is_fifo() {
+            return Some("a FIFO");
+        }
+        if file_type.is_block_device() || file_type.is_char_device() {
+            return Some("a device file");
+        }
+        if file_type.is_socket() {
+            return Some("a socket");
+        }
+    }
+    Some("a special file")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::FileTypePolicy;
+    use std::path::Path;
+
+    #[test]
+    fn open() {
+        for (path, kind) in [
+            ("testdata/corro.svg", None),
+            ("/dev/null", Some("a device file")),
+            ("testdata", Some("a directory")),
+        ] {
+            let path = Path::new(path);
+            assert_eq!(
+                FileTypePolicy::Follow.open(path).is_ok(),
+                kind != Some("a directory")
+            );
+            assert_eq!(
+                FileTypePolicy::Skip.open(path).unwrap().is_some(),
+                kind.is_none()
+            );
+            match FileTypePolicy::Reject.open(path) {
+                Ok(file) => assert!(file.is_some() && kind.is_none()),
+                Err(err) => assert!(err.to_string().contains(kind.unwrap())),
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::ffi::OsStr;
+use std::io::{Error, ErrorKind, Result, Write as _};
+
+/// How `filename` parameters that are not valid UTF-8 are written. Set with
+/// [`FormData::set_filename_encoding`][crate::FormData::set_filename_encoding].
+///
+/// Filenames that are valid UTF-8 are always written as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilenameEncoding {
+    /// Replace invalid sequences with U+FFFD REPLACEMENT CHARACTER.
+    #[default]
+    Lossy,
+    /// Percent-encode the bytes of invalid sequences, as [RFC 7578 § 4.2][rfc7578sec4.2]
+    /// suggests for non-ASCII filenames.
+    ///
+    /// [rfc7578sec4.2]: https://www.rfc-editor.org/rfc/rfc7578.html#section-4.2
+    PercentEncode,
+    /// Fail with an error of kind [`ErrorKind::InvalidInput`].
+    Reject,
+}
+
+impl FilenameEncoding {
+    /// Appends `filename` to `out`. Only [`FilenameEncoding::Lossy`] allocates, and only for
+    /// invalid filenames.
+    pub(crate) fn encode(self, filename: &OsStr, out: &mut Vec<u8>) -> Result<()> {
+        if let Some(filename) = filename.to_str() {
+            out.extend_from_slice(filename.as_bytes());
+            return Ok(());
+        }
+        match self {
+            FilenameEncoding::Lossy => {
+                out.extend_from_slice(filename.to_string_lossy().as_bytes());
+            }
+            FilenameEncoding::PercentEncode => {
+                for chunk in filename.as_encoded_bytes().utf8_chunks() {
+                    out.extend_from_slice(chunk.valid().as_bytes());
+                    for byte in chunk.invalid() {
+                        write!(out, "%{byte:02X}")?;
+                    }
+                }
+            }
+            FilenameEncoding::Reject => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "filename is not valid UTF-8",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::FilenameEncoding;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn encode() {
+        let filename = OsStr::from_bytes(b"corro\xff.svg");
+        for (encoding, expected) in [
+            (FilenameEncoding::Lossy, Some("corro\u{fffd}.svg")),
+            (FilenameEncoding::PercentEncode, Some("corro%FF.svg")),
+            (FilenameEncoding::Reject, None),
+        ] {
+            let mut out = Vec::new();
+            let result = encoding.encode(filename, &mut out);
+            assert_eq!(
+                result.ok().map(|()| String::from_utf8(out).unwrap()),
+                expected.map(str::to_owned)
+            );
+        }
+    }
+}
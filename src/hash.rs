@@ -0,0 +1,85 @@
+#[cfg(feature = "content-md5")]
+use md5::digest::{Digest, Output};
+#[cfg(not(feature = "content-md5"))]
+use sha2::digest::{Digest, Output};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Result, Seek};
+
+/// Hashes a part's body with `D`, which is `body` unless it is read from `file`. `file` is
+/// rewound afterwards.
+pub(crate) fn hash_file<D: Digest>(
+    body: Option<&[u8]>,
+    file: Option<&mut File>,
+) -> Result<Output<D>> {
+    let mut hasher = D::new();
+    read_body(body, file, |data| hasher.update(data))?;
+    Ok(hasher.finalize())
+}
+
+/// Hashes a part's body with both `A` and `B` like [`hash_file`], reading `file` only once.
+#[cfg(all(feature = "dedup", feature = "content-md5"))]
+pub(crate) fn hash_file_pair<A: Digest, B: Digest>(
+    body: Option<&[u8]>,
+    file: Option<&mut File>,
+) -> Result<(Output<A>, Output<B>)> {
+    let (mut a, mut b) = (A::new(), B::new());
+    read_body(body, file, |data| {
+        a.update(data);
+        b.update(data);
+    })?;
+    Ok((a.finalize(), b.finalize()))
+}
+
+fn read_body(body: Option<&[u8]>, file: Option<&mut File>, mut f: impl FnMut(&[u8])) -> Result<()> {
+    match file {
+        Some(file) => {
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                match file.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => f(&buf[..n]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            file.rewind()?;
+        }
+        None => f(body.unwrap_or_default()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "dedup")]
+    #[test]
+    fn hash_file() {
+        use sha2::{Digest, Sha256};
+        use std::io::Seek;
+
+        let mut file = std::fs::File::open("testdata/corro.svg").unwrap();
+        let from_file = super::hash_file::<Sha256>(None, Some(&mut file)).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 0);
+        let body = std::fs::read("testdata/corro.svg").unwrap();
+        assert_eq!(from_file, Sha256::digest(&body));
+        assert_eq!(
+            super::hash_file::<Sha256>(Some(&body), None).unwrap(),
+            from_file
+        );
+    }
+
+    #[cfg(all(feature = "dedup", feature = "content-md5"))]
+    #[test]
+    fn hash_file_pair() {
+        use md5::Md5;
+        use sha2::{Digest, Sha256};
+        use std::io::Seek;
+
+        let mut file = std::fs::File::open("testdata/corro.svg").unwrap();
+        let (sha256, md5) = super::hash_file_pair::<Sha256, Md5>(None, Some(&mut file)).unwrap();
+        assert_eq!(file.stream_position().unwrap(), 0);
+        let body = std::fs::read("testdata/corro.svg").unwrap();
+        assert_eq!(sha256, Sha256::digest(&body));
+        assert_eq!(md5, Md5::digest(&body));
+    }
+}
@@ -0,0 +1,62 @@
+/// A header that [`FormData`][crate::FormData] writes for a part, for
+/// [`FormData::set_header_order`][crate::FormData::set_header_order].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PartHeader {
+    /// `Content-Disposition`, with the part's name and filename. Every part has one.
+    ContentDisposition,
+    /// `Content-Type`, which file parts have.
+    ContentType,
+    /// `Content-ID`, which parts have under
+    /// [`DuplicatePolicy::ContentId`][crate::DuplicatePolicy::ContentId] (with the `dedup`
+    /// feature).
+    ContentId,
+    /// `Content-MD5`, which file parts have under
+    /// [`FormData::set_content_md5`][crate::FormData::set_content_md5] (with the `content-md5`
+    /// feature).
+    ContentMd5,
+}
+
+/// The order headers are written in by default.
+const DEFAULT_ORDER: &[PartHeader] = &[
+    PartHeader::ContentDisposition,
+    PartHeader::ContentType,
+    PartHeader::ContentId,
+    PartHeader::ContentMd5,
+];
+
+impl PartHeader {
+    /// The headers in `order`, then any it leaves out in the default order, each once.
+    pub(crate) fn ordered(order: &[PartHeader]) -> impl Iterator<Item = PartHeader> + '_ {
+        let all = || order.iter().chain(DEFAULT_ORDER).copied();
+        all()
+            .enumerate()
+            .filter(move |&(i, header)| !all().take(i).any(|seen| seen == header))
+            .map(|(_, header)| header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PartHeader::{self, ContentDisposition, ContentId, ContentMd5, ContentType};
+
+    #[test]
+    fn ordered() {
+        for (order, expected) in [
+            (
+                &[][..],
+                [ContentDisposition, ContentType, ContentId, ContentMd5],
+            ),
+            (
+                &[ContentType],
+                [ContentType, ContentDisposition, ContentId, ContentMd5],
+            ),
+            (
+                &[ContentMd5, ContentType, ContentMd5],
+                [ContentMd5, ContentType, ContentDisposition, ContentId],
+            ),
+        ] {
+            assert_eq!(PartHeader::ordered(order).collect::<Vec<_>>(), expected);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use std::fmt::Display;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A primitive integer type, accepted by [`FormData::write_int`][crate::FormData::write_int].
+///
+/// This trait is sealed and implemented for every primitive integer type, from `i8` and `u8` up
+/// to `i128`, `u128`, `isize`, and `usize`.
+pub trait Integer: sealed::Sealed + Copy + Display {}
+
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+            impl Integer for $ty {}
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
@@ -14,27 +14,500 @@
 //! # }
 //! ```
 //!
+//! With the `log` feature enabled, debug-level records are emitted through the [`log`][log] facade
+//! as each part is written and when the document is finished, including byte counts.
+//!
+//! The `leak-check` feature makes dropping a [`FormData`] after writing parts, without calling
+//! [`FormData::finish`] or [`FormData::abort`], panic in builds with debug assertions. This
+//! catches silently truncated uploads during development.
+//!
+//! The `time` feature adds [`FormData::write_datetime`] for [`time::OffsetDateTime`] values.
+//!
+//! The `uuid` feature adds [`FormData::write_uuid`] for [`uuid::Uuid`] values.
+//!
+//! The `csv` feature adds [`FormData::write_csv`] for [`serde::Serialize`] records.
+//!
+//! The `ndjson` feature adds [`FormData::write_ndjson`] for streams of [`serde::Serialize`]
+//! items.
+//!
+//! The `quick-xml` feature adds [`FormData::write_xml`] for [`serde::Serialize`] values.
+//!
+//! The `nested` feature adds [`FormData::write_nested`], for writing [`serde::Serialize`] values
+//! as fields with names like `a[b][c]`.
+//!
+//! The `prost` feature adds [`FormData::write_protobuf`] for [`prost::Message`]s.
+//!
+//! The `tokio` feature adds [`AsyncPartSource`], for part bodies read from a
+//! [`tokio::io::AsyncRead`].
+//!
+//! The `gzip` feature adds [`FormData::gzip`], for compressing the whole document as it is
+//! written.
+//!
+//! The `tokio-util` feature adds [`PartEncoder`], a [`tokio_util::codec::Encoder`] for parts.
+//!
+//! The `stream` feature adds [`FormDataBuilder::into_stream`], for producing a document as a
+//! [`futures_util::Stream`] of [`bytes::Bytes`] chunks as it is polled.
+//!
+//! The `dedup` feature adds [`FormData::set_duplicate_policy`], for leaving out or referring back
+//! to file parts whose bodies were already written.
+//!
+//! The `hmac` feature adds [`FormData::sign_hmac`], for ending the document with an HMAC-SHA256
+//! signature of everything before it.
+//!
+//! The `manifest` feature adds [`FormData::set_manifest_part`], for ending the document with a
+//! JSON part that lists the parts before it along with SHA-256 hashes of their bodies.
+//!
+//! The `content-md5` feature adds [`FormData::set_content_md5`], for adding a `Content-MD5`
+//! header to file parts.
+//!
+//! The `indicatif` feature adds [`FormData::progress_bar`], for showing upload progress on an
+//! [`indicatif::ProgressBar`].
+//!
+//! The `nightly` feature, which requires a nightly compiler, copies part bodies with
+//! `Read::read_buf` where this crate copies them itself, skipping zero-initialization of the
+//! copy buffer.
+//!
 //! Looking for a feature-packed, asynchronous, robust, and well-tested `multipart/form-data`
 //! library that validates things like content types? We hope you find one somewhere!
 //!
+//! [log]: https://docs.rs/log
 //! [rfc7578]: https://www.rfc-editor.org/rfc/rfc7578.html
 
 #![warn(clippy::pedantic)]
+#![cfg_attr(feature = "nightly", feature(read_buf, core_io_borrowed_buf))]
+
+/// The size of the buffer used when copying part bodies ourselves rather than with `io::copy`.
+const COPY_BUF_SIZE: usize = 8 * 1024;
+
+/// Emits a debug-level record with the `log` feature, and nothing without it.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+    };
+}
+
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "axum")]
+pub mod axum;
+mod builder;
+#[cfg(feature = "tokio-util")]
+mod codec;
+#[cfg(feature = "content-md5")]
+mod content_md5;
+mod date;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "dedup")]
+mod dedup;
+mod error;
+mod file_type;
+mod filename;
+#[cfg(any(feature = "dedup", feature = "content-md5"))]
+mod hash;
+mod header_order;
+mod integer;
+mod manifest;
+mod metrics;
+#[cfg(feature = "multer")]
+pub mod multer;
+#[cfg(feature = "nested")]
+mod nested;
+mod options;
+pub mod parse;
+mod part;
+mod part_writer;
+mod progress;
+mod read_ahead;
+mod reader;
+#[cfg(feature = "reqwest")]
+mod reqwest;
+mod reserve;
+#[cfg(feature = "hmac")]
+mod signature;
+mod source;
+#[cfg(feature = "tempfile")]
+mod spool;
+mod tee;
+mod template;
+mod truncate;
+
+pub use crate::builder::FormDataBuilder;
+#[cfg(feature = "tokio-util")]
+pub use crate::codec::PartEncoder;
+#[cfg(feature = "dedup")]
+pub use crate::dedup::DuplicatePolicy;
+pub use crate::error::FormDataError;
+#[cfg(any(feature = "gzip", feature = "hmac"))]
+pub use crate::error::WrapError;
+pub use crate::file_type::FileTypePolicy;
+pub use crate::filename::FilenameEncoding;
+pub use crate::header_order::PartHeader;
+pub use crate::integer::Integer;
+pub use crate::manifest::ManifestEntry;
+pub use crate::metrics::Metrics;
+#[cfg(feature = "nested")]
+pub use crate::nested::NestedNaming;
+pub use crate::options::FormDataOptions;
+pub use crate::part::Part;
+pub use crate::part_writer::PartWriter;
+pub use crate::progress::Progress;
+#[cfg(feature = "stream")]
+pub use crate::reader::FormDataStream;
+pub use crate::reader::{FormDataReader, PauseHandle};
+pub use crate::reserve::Reserve;
+#[cfg(feature = "hmac")]
+pub use crate::signature::HmacWriter;
+#[cfg(feature = "tokio")]
+pub use crate::source::AsyncPartSource;
+pub use crate::source::{PartSource, ReaderSource};
+#[cfg(feature = "tempfile")]
+pub use crate::spool::{Spooled, SpooledWriter, StagedSource};
+pub use crate::tee::Tee;
+pub use crate::template::{FormDataTemplate, PlannedPart};
+pub use crate::truncate::Truncate;
 
+#[cfg(feature = "manifest")]
+use crate::manifest::HashingWriter;
+use crate::part::DispositionParams;
+use crate::read_ahead::ReadAhead;
 use rand::{thread_rng, RngCore};
-use std::ffi::OsStr;
+#[cfg(feature = "manifest")]
+use sha2::Digest;
+#[cfg(any(feature = "dedup", feature = "manifest"))]
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
 use std::fs::File;
-use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::num::NonZeroU64;
 use std::path::Path;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Writes a whole `multipart/form-data` document described by `parts` to `writer`, returning the
+/// writer.
+///
+/// This drives [`FormData`] entirely from plain data, which makes it a convenient entry point for
+/// fuzz harnesses and differential tests against other multipart implementations.
+///
+/// ```
+/// # use form_data_builder::Part;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let document = form_data_builder::build(Vec::new(), "boundary", &[Part::text("cute", "yes")])?;
+/// assert_eq!(
+///     document,
+///     b"--boundary\r\n\
+///       Content-Disposition: form-data; name=\"cute\"\r\n\
+///       \r\n\
+///       yes\r\n\
+///       --boundary--\r\n",
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `boundary` is invalid (see [`FormData::with_boundary`]) or if the writer
+/// fails.
+pub fn build<W: Write>(writer: W, boundary: &str, parts: &[Part<'_>]) -> Result<W> {
+    let mut form = FormData::with_boundary(writer, boundary)?;
+    let result = parts.iter().try_for_each(|part| form.write_part(part));
+    form.finish_or_abort(result)
+}
+
+/// Checks whether writing should stop, because of [`FormData::set_cancellation`] or
+/// [`FormData::set_time_limit`].
+fn check_interrupted(cancelled: Option<&AtomicBool>, deadline: Option<Instant>) -> Result<()> {
+    if cancelled.is_some_and(|cancelled| cancelled.load(Ordering::Relaxed)) {
+        return Err(Error::other("writing the document was cancelled"));
+    }
+    if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+        return Err(Error::new(
+            ErrorKind::TimedOut,
+            "writing the document took longer than the time limit",
+        ));
+    }
+    Ok(())
+}
+
+/// Checks `name` for [`FormData::set_strict_names`].
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    let reason = if name.is_empty() {
+        "it is empty"
+    } else if name.chars().all(char::is_whitespace) {
+        "it is only whitespace"
+    } else if name.contains('"') {
+        "it contains a quotation mark"
+    } else if name.contains(char::is_control) {
+        "it contains a control character"
+    } else {
+        return Ok(());
+    };
+    Err(FormDataError::InvalidName {
+        name: name.to_owned(),
+        reason,
+    }
+    .into())
+}
+
+/// Converts lone `\n` and `\r` line breaks in `value` to `\r\n`, for
+/// [`FormData::set_normalize_newlines`].
+fn normalize_newlines(value: &[u8]) -> Cow<'_, [u8]> {
+    let mut breaks = value.iter().enumerate().filter(|(i, b)| match b {
+        b'\r' => value.get(i + 1) != Some(&b'\n'),
+        b'\n' => *i == 0 || value[i - 1] != b'\r',
+        _ => false,
+    });
+    if breaks.next().is_none() {
+        return Cow::Borrowed(value);
+    }
+    let mut normalized = Vec::with_capacity(value.len() + value.len() / 8);
+    let mut bytes = value.iter().copied().peekable();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'\r' => {
+                bytes.next_if_eq(&b'\n');
+                normalized.extend_from_slice(b"\r\n");
+            }
+            b'\n' => normalized.extend_from_slice(b"\r\n"),
+            b => normalized.push(b),
+        }
+    }
+    Cow::Owned(normalized)
+}
+
+/// The buffer part bodies are copied through when they aren't copied with `io::copy`. With the
+/// `nightly` feature it is left uninitialized and filled with `Read::read_buf`.
+struct CopyBuf {
+    #[cfg(not(feature = "nightly"))]
+    buf: [u8; COPY_BUF_SIZE],
+    #[cfg(feature = "nightly")]
+    buf: [std::mem::MaybeUninit<u8>; COPY_BUF_SIZE],
+}
+
+impl CopyBuf {
+    fn new() -> CopyBuf {
+        CopyBuf {
+            #[cfg(not(feature = "nightly"))]
+            buf: [0; COPY_BUF_SIZE],
+            #[cfg(feature = "nightly")]
+            buf: [std::mem::MaybeUninit::uninit(); COPY_BUF_SIZE],
+        }
+    }
+
+    /// Reads once from `reader` into at most `len` bytes of the buffer, returning what was read.
+    #[cfg(not(feature = "nightly"))]
+    fn read<R: Read + ?Sized>(&mut self, reader: &mut R, len: usize) -> Result<&[u8]> {
+        let n = reader.read(&mut self.buf[..len])?;
+        Ok(&self.buf[..n])
+    }
+
+    /// Reads once from `reader` into at most `len` bytes of the buffer, returning what was read.
+    #[cfg(feature = "nightly")]
+    fn read<R: Read + ?Sized>(&mut self, reader: &mut R, len: usize) -> Result<&[u8]> {
+        let mut buf = std::io::BorrowedBuf::from(&mut self.buf[..len]);
+        reader.read_buf(buf.unfilled())?;
+        Ok(buf.into_filled())
+    }
+}
+
+/// Reads from each of a list of readers in turn, for [`FormData::write_file_concat`].
+struct Concat<I: Iterator> {
+    current: Option<I::Item>,
+    rest: I,
+}
+
+impl<I: Iterator> Read for Concat<I>
+where
+    I::Item: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while let Some(reader) = &mut self.current {
+            match reader.read(buf)? {
+                0 => self.current = self.rest.next(),
+                n => return Ok(n),
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Wraps the reader of a part's body to find out whether an error came from it, and how far it
+/// got.
+struct BodyReader<'a, R: ?Sized> {
+    reader: &'a mut R,
+    offset: u64,
+    failed: bool,
+}
+
+impl<R: Read + ?Sized> Read for BodyReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.reader.read(buf).inspect_err(|err| {
+            self.failed = err.kind() != ErrorKind::Interrupted;
+        })?;
+        self.offset += n as u64;
+        Ok(n)
+    }
+
+    #[cfg(feature = "nightly")]
+    fn read_buf(&mut self, mut cursor: std::io::BorrowedCursor<'_>) -> Result<()> {
+        let before = cursor.written();
+        let result = self.reader.read_buf(cursor.reborrow());
+        self.offset += (cursor.written() - before) as u64;
+        result.inspect_err(|err| self.failed = err.kind() != ErrorKind::Interrupted)
+    }
+}
 
 /// `multipart/form-data` document builder.
 ///
 /// See the [module documentation][`crate`] for an example.
-#[derive(Debug, Clone)]
+///
+/// The `Debug` output lists the parts written so far (see [`FormData::describe`]) but never the
+/// writer, so it is safe to log.
+///
+/// Cloning a `FormData` clones its writer along with everything written so far, and the two
+/// then carry on independently. The boundary is shared rather than copied. To carry on with a
+/// different writer instead, use [`FormData::fork`].
+#[derive(Clone)]
 pub struct FormData<W> {
     writer: Option<W>,
-    boundary: String,
+    boundary: Boundary,
+    parts: Vec<PartSummary>,
+    /// The number of bytes written to the writer so far.
+    written: u64,
+    /// Where the last complete part ended.
+    progress: Progress,
+    /// How many parts the file type policy left out, which count towards `progress` so that it
+    /// lines up with the parts that were asked for.
+    skipped: usize,
+    metrics: Option<Arc<dyn Metrics + Send + Sync>>,
+    options: FormDataOptions,
+    /// When the first part and the current part started, if anything is measuring.
+    timing: Option<(Instant, Instant)>,
+    /// Scratch space for part headers and the closing delimiter, reused so that writing a part
+    /// doesn't allocate. Each is assembled here and issued as a single `write_all`, so it reaches
+    /// the writer in one piece.
+    header: Vec<u8>,
+    /// Whether `finish()` has completed, so that `written` is the length of the whole document.
+    finished: bool,
+    /// The `Content-Encoding` the writer applies, if any, in which case `written` is not the
+    /// length of what is sent.
+    content_encoding: Option<&'static str>,
+    /// Hashes of the bodies of file parts written under a [`DuplicatePolicy`], each with the
+    /// number of parts before it.
+    #[cfg(feature = "dedup")]
+    digests: Vec<(usize, [u8; 32])>,
+    /// The `Content-ID` header for the part about to be written, if it has one.
+    #[cfg(feature = "dedup")]
+    content_id: Option<String>,
+    /// The `Content-MD5` header for the part about to be written, if it has one.
+    #[cfg(feature = "content-md5")]
+    content_md5: Option<String>,
+    /// The hash of the current part's body so far, if a manifest part is set.
+    #[cfg(feature = "manifest")]
+    sha256: Option<Sha256>,
+}
+
+/// A multipart boundary. Boundaries that live forever are used as they are, and others are
+/// shared between clones and forks rather than copied.
+#[derive(Clone)]
+enum Boundary {
+    Static(&'static str),
+    Shared(Arc<str>),
+}
+
+impl std::ops::Deref for Boundary {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Boundary::Static(boundary) => boundary,
+            Boundary::Shared(boundary) => boundary,
+        }
+    }
+}
+
+/// What [`FormData::describe`] and the `Debug` impl know about a written part.
+#[derive(Clone)]
+struct PartSummary {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    /// The start of a non-file field's value.
+    value: Option<String>,
+    sensitive: bool,
+    size: u64,
+    /// Where the body starts in the document.
+    offset: u64,
+    #[cfg(feature = "manifest")]
+    sha256: Option<String>,
+}
+
+impl PartSummary {
+    /// How much of a field's value is kept for logging.
+    const VALUE_PREVIEW: usize = 64;
+
+    fn value(&self) -> Option<&str> {
+        if self.sensitive {
+            Some("[REDACTED]")
+        } else {
+            self.value.as_deref()
+        }
+    }
+}
+
+#[allow(clippy::missing_fields_in_debug)] // offsets are for `manifest()`, and would clutter logs
+impl fmt::Debug for PartSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Part");
+        s.field("name", &self.name);
+        if let Some(filename) = &self.filename {
+            s.field("filename", filename);
+        }
+        if let Some(content_type) = &self.content_type {
+            s.field("content_type", content_type);
+        }
+        match self.value() {
+            Some(value) if self.sensitive => s.field("value", &format_args!("{value}")),
+            Some(value) => s.field("value", &value).field("size", &self.size),
+            None => s.field("size", &self.size),
+        };
+        s.finish()
+    }
+}
+
+/// With the `leak-check` feature and debug assertions enabled, panics if parts were written but
+/// the document was neither finished nor aborted.
+#[cfg(all(feature = "leak-check", debug_assertions))]
+impl<W> Drop for FormData<W> {
+    fn drop(&mut self) {
+        assert!(
+            self.writer.is_none() || self.parts.is_empty() || std::thread::panicking(),
+            "FormData dropped after writing {} parts without `finish()` or `abort()`",
+            self.parts.len()
+        );
+    }
+}
+
+impl<W> fmt::Debug for FormData<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never show the writer: it may hold the entire document
+        f.debug_struct("FormData")
+            .field("boundary", &&*self.boundary)
+            .field("finished", &self.writer.is_none())
+            .field("parts", &self.parts)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<W: Write> FormData<W> {
@@ -58,200 +531,3130 @@ impl<W: Write> FormData<W> {
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .expect("system time should be after the Unix epoch");
-        (&mut buf[..4]).copy_from_slice(&now.subsec_nanos().to_ne_bytes());
-        (&mut buf[4..12]).copy_from_slice(&now.as_secs().to_ne_bytes());
+        buf[..4].copy_from_slice(&now.subsec_nanos().to_ne_bytes());
+        buf[4..12].copy_from_slice(&now.as_secs().to_ne_bytes());
         thread_rng().fill_bytes(&mut buf[12..]);
 
-        let boundary = format!("{:->68}", base64::encode_config(&buf, base64::URL_SAFE));
+        let boundary = format!("{:->68}", base64::encode_config(buf, base64::URL_SAFE));
 
-        FormData {
-            writer: Some(writer),
-            boundary,
-        }
+        FormData::with_valid_boundary(writer, Boundary::Shared(boundary.into()))
     }
 
-    /// Finish the `multipart/form-data` document, returning the writer.
+    /// Starts writing a `multipart/form-data` document to `writer` with `options`, generating a
+    /// boundary like [`FormData::new`]. See [`FormDataOptions`] for an example.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`].
+    pub fn with_options(writer: W, options: FormDataOptions) -> FormData<W> {
+        let mut form = FormData::new(writer);
+        form.options = options;
+        form
+    }
+
+    /// Starts writing a `multipart/form-data` document to `writer`, using a fixed `boundary`.
+    ///
+    /// This is for reproducible output, such as in tests. The boundary must not appear anywhere
+    /// in the document's contents; [`FormData::new`] generates one that won't.
     ///
     /// ```
     /// # use form_data_builder::FormData;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let mut form = FormData::new(Vec::new());
-    /// // ... things happen ...
-    /// let document: Vec<u8> = form.finish()?;
+    /// let mut form = FormData::with_boundary(Vec::new(), "boundary")?;
+    /// assert_eq!(form.content_type_header(), "multipart/form-data; boundary=boundary");
     /// # Ok(())
     /// # }
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if `finish()` has already been called or if the writer fails.
-    pub fn finish(&mut self) -> Result<W> {
-        let mut writer = self
-            .writer
-            .take()
-            .ok_or_else(|| Error::new(ErrorKind::Other, "you can only finish once"))?;
-        write!(writer, "--{}--\r\n", self.boundary)?;
-        Ok(writer)
+    /// Returns an error of kind [`ErrorKind::InvalidInput`] if `boundary` is not a valid
+    /// boundary per [RFC 2046 § 5.1.1][rfc2046].
+    ///
+    /// [rfc2046]: https://www.rfc-editor.org/rfc/rfc2046.html#section-5.1.1
+    pub fn with_boundary(writer: W, boundary: &str) -> Result<FormData<W>> {
+        if !parse::is_valid_boundary(boundary) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid boundary"));
+        }
+        Ok(FormData::with_valid_boundary(
+            writer,
+            Boundary::Shared(boundary.into()),
+        ))
     }
 
-    fn write_header(
-        &mut self,
-        name: &str,
-        filename: Option<&OsStr>,
-        content_type: Option<&str>,
-    ) -> Result<&mut W> {
-        let writer = self.writer.as_mut().ok_or_else(|| {
-            Error::new(
-                ErrorKind::Other,
-                "this method cannot be used after using `finish()`",
-            )
-        })?;
-
-        write!(writer, "--{}\r\n", self.boundary)?;
-
-        write!(writer, "Content-Disposition: form-data; name=\"{}\"", name)?;
-        if let Some(filename) = filename {
-            write!(writer, "; filename=\"{}\"", filename.to_string_lossy())?;
+    /// Like [`FormData::with_boundary`], but for a boundary that lives forever, such as a string
+    /// literal or a `const`. The boundary is used as it is, without being copied.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// const BOUNDARY: &str = "boundary";
+    ///
+    /// let mut form = FormData::with_static_boundary(Vec::new(), BOUNDARY)?;
+    /// assert_eq!(form.boundary(), BOUNDARY);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormData::with_boundary`].
+    pub fn with_static_boundary(writer: W, boundary: &'static str) -> Result<FormData<W>> {
+        if !parse::is_valid_boundary(boundary) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid boundary"));
         }
-        write!(writer, "\r\n")?;
+        Ok(FormData::with_valid_boundary(
+            writer,
+            Boundary::Static(boundary),
+        ))
+    }
 
-        if let Some(content_type) = content_type {
-            write!(writer, "Content-Type: {}\r\n", content_type)?;
+    fn with_valid_boundary(writer: W, boundary: Boundary) -> FormData<W> {
+        FormData {
+            writer: Some(writer),
+            boundary,
+            parts: Vec::new(),
+            written: 0,
+            progress: Progress::default(),
+            skipped: 0,
+            metrics: None,
+            options: FormDataOptions::default(),
+            timing: None,
+            header: Vec::new(),
+            finished: false,
+            content_encoding: None,
+            #[cfg(feature = "dedup")]
+            digests: Vec::new(),
+            #[cfg(feature = "dedup")]
+            content_id: None,
+            #[cfg(feature = "content-md5")]
+            content_md5: None,
+            #[cfg(feature = "manifest")]
+            sha256: None,
         }
+    }
 
-        write!(writer, "\r\n")?;
-        Ok(writer)
+    /// The writer, unless `finish()` has been called.
+    #[must_use]
+    pub fn get_ref(&self) -> Option<&W> {
+        self.writer.as_ref()
     }
 
-    /// Write a non-file field to the document.
+    /// Carries on the document from this point in a second builder that writes to `writer`.
+    ///
+    /// The new builder has the same boundary and settings as this one, and knows about the parts
+    /// written so far, but writes only what comes next: `writer` should already hold a copy of
+    /// what this builder has written. This builder is unaffected. This is useful for sending
+    /// many documents that start with the same parts.
     ///
     /// ```
     /// # use form_data_builder::FormData;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// # let mut form = FormData::new(Vec::new());
-    /// form.write_field("butts", "lol")?;
+    /// let mut form = FormData::new(Vec::new());
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// let prefix = form.get_ref().unwrap().clone();
+    ///
+    /// for cute in ["yes", "very"] {
+    ///     let mut fork = form.fork(prefix.clone())?;
+    ///     fork.write_field("cute", cute)?;
+    ///     let document = fork.finish()?;
+    ///     // ... send `document` with `fork.content_type_header()` ...
+    /// }
+    /// # form.finish()?;
     /// # Ok(())
     /// # }
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if `finish()` has already been called or if the writer fails.
-    pub fn write_field(&mut self, name: &str, value: &str) -> Result<()> {
-        let writer = self.write_header(name, None, None)?;
-        write!(writer, "{}\r\n", value)
+    /// Returns an error if `finish()` has already been called.
+    pub fn fork<W2: Write>(&self, writer: W2) -> Result<FormData<W2>> {
+        if self.writer.is_none() {
+            return Err(Error::other(
+                "this method cannot be used after using `finish()`",
+            ));
+        }
+        Ok(self.with_writer(writer))
     }
 
-    /// Write a file field to the document, copying the data from `reader`.
-    ///
-    /// [RFC 7578 § 4.2](rfc7578sec4.2) advises "a name for the file SHOULD be supplied", but
-    /// "isn't mandatory for cases where the file name isn't availbale or is meaningless or
-    /// private".
+    /// Duplicates everything written from now on to `second`, returning a builder that writes to
+    /// both writers. `finish()` returns both, as a [`Tee`].
     ///
-    /// [rfc7578sec4.2]: https://www.rfc-editor.org/rfc/rfc7578.html#section-4.2
+    /// This is useful for keeping a local copy of an upload for debugging.
     ///
     /// ```
     /// # use form_data_builder::FormData;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// # let mut form = FormData::new(Vec::new());
-    /// use std::io::Cursor;
+    /// # let socket = Vec::new();
+    /// let mut form = FormData::new(socket).tee(Vec::new())?;
+    /// form.write_field("cute", "yes")?;
+    /// let (socket, capture) = form.finish()?.into_inner();
+    /// assert_eq!(socket, capture);
+    /// # Ok(())
+    /// # }
+    /// ```
     ///
-    /// const CORRO: &[u8] = include_bytes!("../testdata/corro.svg");
-    /// form.write_file("corro", Cursor::new(CORRO), None, "image/svg+xml")?;
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called.
+    pub fn tee<W2: Write>(mut self, second: W2) -> Result<FormData<Tee<W, W2>>> {
+        let first = self
+            .writer
+            .take()
+            .ok_or_else(|| Error::other("this method cannot be used after using `finish()`"))?;
+        Ok(self.with_writer(Tee::new(first, second)))
+    }
+
+    /// Boxes the writer, returning a [`FormDataBoxed`] in the same state, for code that holds
+    /// builders without naming their writer types.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, FormDataBoxed};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut forms: Vec<FormDataBoxed> = vec![
+    ///     FormData::new(Vec::new()).into_boxed()?,
+    ///     FormData::new(std::io::sink()).into_boxed()?,
+    /// ];
+    /// for form in &mut forms {
+    ///     form.write_field("cute", "yes")?;
+    ///     form.finish()?;
+    /// }
     /// # Ok(())
     /// # }
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if `finish()` has already been called or if the writer fails.
-    pub fn write_file<R: Read>(
-        &mut self,
-        name: &str,
-        mut reader: R,
-        filename: Option<&OsStr>,
-        content_type: &str,
-    ) -> Result<()> {
-        let writer = self.write_header(name, filename, Some(content_type))?;
-        std::io::copy(&mut reader, writer)?;
-        write!(writer, "\r\n")
+    /// Returns an error if `finish()` has already been called.
+    pub fn into_boxed(mut self) -> Result<FormDataBoxed>
+    where
+        W: Send + 'static,
+    {
+        let writer = self
+            .writer
+            .take()
+            .ok_or_else(|| Error::other("this method cannot be used after using `finish()`"))?;
+        Ok(self.with_writer(Box::new(writer)))
     }
 
-    /// Write a file field to the document, opening the file at `path` and copying its data.
+    /// Compresses the document with gzip as it is written, returning a builder that writes to a
+    /// [`flate2::write::GzEncoder`]. Finish it with [`FormData::finish_gzip`].
     ///
-    /// This method detects the `filename` parameter from the `path`. To avoid this, use
-    /// [`FormData::write_file`].
+    /// This is for endpoints that accept compressed request bodies. [`FormData::headers`] then
+    /// includes `Content-Encoding: gzip`, and leaves out `Content-Length`, since the compressed
+    /// length isn't known here.
     ///
     /// ```
     /// # use form_data_builder::FormData;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// # let mut form = FormData::new(Vec::new());
+    /// let mut form = FormData::new(Vec::new()).gzip()?;
     /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// let compressed = form.finish_gzip()?;
+    /// assert_eq!(form.headers()[1], ("Content-Encoding", "gzip".to_owned()));
     /// # Ok(())
     /// # }
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if `finish()` has already been called or if the writer fails.
-    pub fn write_path<P: AsRef<Path>>(
-        &mut self,
-        name: &str,
-        path: P,
-        content_type: &str,
-    ) -> Result<()> {
-        self.write_file(
-            name,
-            &mut File::open(path.as_ref())?,
-            path.as_ref().file_name(),
-            content_type,
-        )
+    /// Returns a [`WrapError`] holding this builder, unchanged, if anything has been written yet
+    /// or if `finish()` has already been called.
+    #[cfg(feature = "gzip")]
+    pub fn gzip(
+        mut self,
+    ) -> std::result::Result<FormData<flate2::write::GzEncoder<W>>, WrapError<W>> {
+        if self.written > 0 {
+            return Err(WrapError::new(
+                "this method cannot be used after writing a part",
+                self,
+            ));
+        }
+        let Some(writer) = self.writer.take() else {
+            return Err(WrapError::new(
+                "this method cannot be used after using `finish()`",
+                self,
+            ));
+        };
+        let mut form = self.with_writer(flate2::write::GzEncoder::new(
+            writer,
+            flate2::Compression::default(),
+        ));
+        form.content_encoding = Some("gzip");
+        Ok(form)
     }
 
-    /// Returns the value of the `Content-Type` header that corresponds with the document.
+    /// Signs the document with HMAC-SHA256 as it is written, returning a builder whose
+    /// [`FormData::finish_signed`] adds a final non-file field called `name` with the signature,
+    /// in lowercase hex.
+    ///
+    /// The signature covers every byte of the document before the signature field, which is from
+    /// the start of the document up to the `--boundary` line that starts the field. A receiver
+    /// with the same `key` can check it by finding that line and hashing what comes before it.
     ///
     /// ```
     /// # use form_data_builder::FormData;
-    /// # struct Request;
-    /// # impl Request {
-    /// #     fn with_header(&mut self, key: &str, value: String) {}
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormData::new(Vec::new()).sign_hmac(b"shared secret", "signature")?;
+    /// form.write_field("cute", "yes")?;
+    /// let document = form.finish_signed()?;
+    /// # Ok(())
     /// # }
-    /// # let mut request = Request;
-    /// # let mut form = FormData::new(Vec::new());
-    /// // your HTTP client's API may vary
-    /// request.with_header("Content-Type", form.content_type_header());
     /// ```
-    pub fn content_type_header(&self) -> String {
-        format!("multipart/form-data; boundary={}", self.boundary)
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WrapError`] holding this builder, unchanged, if anything has been written yet
+    /// or if `finish()` has already been called.
+    #[cfg(feature = "hmac")]
+    pub fn sign_hmac(
+        mut self,
+        key: &[u8],
+        name: &str,
+    ) -> std::result::Result<FormData<Tee<W, HmacWriter>>, WrapError<W>> {
+        if self.written > 0 {
+            return Err(WrapError::new(
+                "this method cannot be used after writing a part",
+                self,
+            ));
+        }
+        let Some(writer) = self.writer.take() else {
+            return Err(WrapError::new(
+                "this method cannot be used after using `finish()`",
+                self,
+            ));
+        };
+        Ok(self.with_writer(Tee::new(writer, HmacWriter::new(key, name))))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::FormData;
-    use std::ffi::OsString;
-    use std::io::Cursor;
-    use std::path::Path;
+    /// Shows the progress of writing `parts` on `bar`, returning a builder that counts everything
+    /// written from now on toward it. `finish()` returns the writer along with the counter, as a
+    /// [`Tee`].
+    ///
+    /// The bar's length is set to the size of the document once `parts` are written, so it fills
+    /// up as the document is finished.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, Part};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let socket = Vec::new();
+    /// let parts = [Part::path("corro", "testdata/corro.svg", "image/svg+xml")];
+    /// let bar = indicatif::ProgressBar::hidden();
+    /// let mut form = FormData::new(socket).progress_bar(&bar, &parts)?;
+    /// for part in &parts {
+    ///     form.write_part(part)?;
+    /// }
+    /// let (socket, _) = form.finish()?.into_inner();
+    /// assert_eq!(Some(bar.position()), bar.length());
+    /// bar.finish();
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called.
+    #[cfg(feature = "indicatif")]
+    pub fn progress_bar(
+        self,
+        bar: &indicatif::ProgressBar,
+        parts: &[Part<'_>],
+    ) -> Result<FormData<Tee<W, indicatif::ProgressBarIter<std::io::Sink>>>> {
+        bar.set_length(self.written + self.predicted_len(parts));
+        bar.set_position(self.written);
+        self.tee(bar.wrap_write(std::io::sink()))
+    }
 
-    /// This test uses a `multipart/form-data` document generated by Firefox as a test case.
-    #[test]
-    fn smoke_test() {
-        const CORRECT: &[u8] = include_bytes!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/testdata/form-data.bin"
-        ));
-        const CORRO: &str =
-            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/corro.svg"));
-        const TEXT_A: &str =
-            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/text-a.txt"));
-        const TEXT_B: &str =
-            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/text-b.txt"));
+    /// A builder in the same state as this one, writing to `writer`.
+    fn with_writer<W2>(&self, writer: W2) -> FormData<W2> {
+        FormData {
+            writer: Some(writer),
+            boundary: self.boundary.clone(),
+            parts: self.parts.clone(),
+            written: self.written,
+            progress: self.progress,
+            skipped: self.skipped,
+            metrics: self.metrics.clone(),
+            options: self.options.clone(),
+            timing: self.timing,
+            header: Vec::new(),
+            finished: false,
+            content_encoding: self.content_encoding,
+            #[cfg(feature = "dedup")]
+            digests: self.digests.clone(),
+            #[cfg(feature = "dedup")]
+            content_id: None,
+            #[cfg(feature = "content-md5")]
+            content_md5: None,
+            #[cfg(feature = "manifest")]
+            sha256: None,
+        }
+    }
 
-        let mut form = FormData::new(Vec::new());
-        assert_eq!(form.boundary.len(), 68);
-        assert_eq!(form.boundary[..(36)], "-".repeat(36));
+    /// Finish the `multipart/form-data` document, returning the writer.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormData::new(Vec::new());
+    /// // ... things happen ...
+    /// let document: Vec<u8> = form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn finish(&mut self) -> Result<W> {
+        #[cfg(feature = "manifest")]
+        if let (Some(name), Some(_)) = (&self.options.manifest_part, &self.writer) {
+            let name = name.clone();
+            let json = manifest::to_json(&self.manifest());
+            self.write_file(&name, json.as_bytes(), None, "application/json")?;
+        }
+        let mut writer = self
+            .writer
+            .take()
+            .ok_or_else(|| Error::other("you can only finish once"))?;
+        let close = &mut self.header;
+        close.clear();
+        close.extend_from_slice(b"--");
+        close.extend_from_slice(self.boundary.as_bytes());
+        close.extend_from_slice(b"--\r\n");
+        writer.write_all(close)?;
+        self.written += close.len() as u64;
+        debug!(
+            "finished multipart/form-data document: {} parts, {} bytes",
+            self.parts.len(),
+            self.written
+        );
+        if let (Some(metrics), Some((started, _))) = (&self.metrics, self.timing) {
+            metrics.finished(self.written, started.elapsed());
+        }
+        self.finished = true;
+        Ok(writer)
+    }
+
+    /// Abandons the document without writing the closing delimiter, returning the writer, or
+    /// `None` if the document was already finished or aborted.
+    ///
+    /// The document is left incomplete, so a server receiving it should reject it. Use this to
+    /// drop a document deliberately, such as after an error, without tripping the `leak-check`
+    /// feature.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormData::new(Vec::new());
+    /// form.write_field("cute", "yes")?;
+    /// // ... the request was cancelled ...
+    /// let partial = form.abort();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn abort(&mut self) -> Option<W> {
+        let writer = self.writer.take();
+        if writer.is_some() {
+            debug!(
+                "aborted multipart/form-data document: {} parts, {} bytes",
+                self.parts.len(),
+                self.written
+            );
+        }
+        writer
+    }
+
+    /// Finishes the document if writing its parts succeeded, and aborts it otherwise.
+    pub(crate) fn finish_or_abort(&mut self, result: Result<()>) -> Result<W> {
+        match result {
+            Ok(()) => self.finish(),
+            Err(err) => {
+                self.abort();
+                Err(err)
+            }
+        }
+    }
+
+    /// Checks that another part can be started, and starts timing it.
+    fn begin_part(&mut self) -> Result<()> {
+        if self.writer.is_none() {
+            return Err(Error::other(
+                "this method cannot be used after using `finish()`",
+            ));
+        }
+        if self.metrics.is_some() || self.options.time_limit.is_some() {
+            let now = Instant::now();
+            self.timing = Some((self.timing.map_or(now, |(started, _)| started), now));
+        }
+        check_interrupted(self.options.cancellation.as_deref(), self.deadline())
+    }
+
+    /// Writes the boundary line and then `headers` as they are.
+    fn write_raw_header(&mut self, name: &str, headers: &[u8]) -> Result<()> {
+        self.begin_part()?;
+        let writer = self.writer.as_mut().expect("checked by begin_part");
+
+        let header = &mut self.header;
+        header.clear();
+        header.extend_from_slice(b"--");
+        header.extend_from_slice(self.boundary.as_bytes());
+        header.extend_from_slice(b"\r\n");
+        header.extend_from_slice(headers);
+        writer
+            .write_all(header)
+            .map_err(|err| FormDataError::in_part(name, err))?;
+        self.written += header.len() as u64;
+        Ok(())
+    }
+
+    fn write_header(
+        &mut self,
+        name: &str,
+        filename: Option<&OsStr>,
+        content_type: Option<&str>,
+        params: &DispositionParams,
+    ) -> Result<()> {
+        self.begin_part()?;
+        let mut header = std::mem::take(&mut self.header);
+        let result = self.render_header(&mut header, name, filename, content_type, params);
+        #[cfg(feature = "dedup")]
+        {
+            self.content_id = None;
+        }
+        #[cfg(feature = "content-md5")]
+        {
+            self.content_md5 = None;
+        }
+        let writer = self.writer.as_mut().expect("checked by begin_part");
+        let result = result.and_then(|()| writer.write_all(&header));
+        self.header = header;
+        result.map_err(|err| FormDataError::in_part(name, err))?;
+        self.written += self.header.len() as u64;
+
+        self.parts.push(PartSummary {
+            name: name.to_owned(),
+            filename: filename.map(|filename| filename.to_string_lossy().into_owned()),
+            content_type: content_type.map(str::to_owned),
+            value: None,
+            sensitive: false,
+            size: 0,
+            offset: self.written,
+            #[cfg(feature = "manifest")]
+            sha256: None,
+        });
+        #[cfg(feature = "manifest")]
+        {
+            self.sha256 = self.options.manifest_part.is_some().then(Sha256::new);
+        }
+        Ok(())
+    }
+
+    /// Renders the boundary line and headers of a part into `header`, replacing its contents.
+    fn render_header(
+        &self,
+        header: &mut Vec<u8>,
+        name: &str,
+        filename: Option<&OsStr>,
+        content_type: Option<&str>,
+        params: &DispositionParams,
+    ) -> Result<()> {
+        if self.options.strict_names {
+            check_name(name)?;
+        }
+        header.clear();
+        header.extend_from_slice(b"--");
+        header.extend_from_slice(self.boundary.as_bytes());
+        header.extend_from_slice(b"\r\n");
+        let order = if self.options.canonical {
+            &[]
+        } else {
+            &self.options.header_order[..]
+        };
+        for part_header in PartHeader::ordered(order) {
+            match part_header {
+                PartHeader::ContentDisposition => {
+                    header.extend_from_slice(b"Content-Disposition: form-data; name=\"");
+                    header.extend_from_slice(name.as_bytes());
+                    header.push(b'"');
+                    if let Some(filename) = filename {
+                        header.extend_from_slice(b"; filename=\"");
+                        self.options.filename_encoding.encode(filename, header)?;
+                        header.push(b'"');
+                    }
+                    params.write(header);
+                    header.extend_from_slice(b"\r\n");
+                }
+                PartHeader::ContentType => {
+                    if let Some(content_type) = content_type {
+                        header.extend_from_slice(b"Content-Type: ");
+                        if self.options.canonical {
+                            let canonical = parse::canonical_content_type(content_type);
+                            header.extend_from_slice(canonical.as_bytes());
+                        } else {
+                            header.extend_from_slice(content_type.as_bytes());
+                        }
+                        header.extend_from_slice(b"\r\n");
+                    }
+                }
+                PartHeader::ContentId =>
+                {
+                    #[cfg(feature = "dedup")]
+                    if let Some(content_id) = &self.content_id {
+                        header.extend_from_slice(b"Content-ID: ");
+                        header.extend_from_slice(content_id.as_bytes());
+                        header.extend_from_slice(b"\r\n");
+                    }
+                }
+                PartHeader::ContentMd5 =>
+                {
+                    #[cfg(feature = "content-md5")]
+                    if let Some(content_md5) = &self.content_md5 {
+                        header.extend_from_slice(b"Content-MD5: ");
+                        header.extend_from_slice(content_md5.as_bytes());
+                        header.extend_from_slice(b"\r\n");
+                    }
+                }
+            }
+        }
+        header.extend_from_slice(b"\r\n");
+        Ok(())
+    }
+
+    /// Copies a part's body from `file`, reading ahead if asked to.
+    fn copy_file(&mut self, file: File) -> Result<u64> {
+        if self.options.read_ahead {
+            self.copy_body(&mut ReadAhead::new(file))
+        } else {
+            let capacity = self.options.file_buffer_size;
+            self.copy_body(&mut BufReader::with_capacity(capacity, file))
+        }
+    }
+
+    /// The disposition parameters for a file part, filled in from the file's metadata if
+    /// [`FormData::set_file_metadata`] is on.
+    fn file_params(&self, file: &File, params: DispositionParams) -> Result<DispositionParams> {
+        if self.options.file_metadata {
+            Ok(params.or_metadata(&file.metadata()?))
+        } else {
+            Ok(params)
+        }
+    }
+
+    /// Copies a part's body from `reader`, returning its size.
+    ///
+    /// Errors from `reader` become [`FormDataError::PartBody`] errors.
+    fn copy_body<R: Read + ?Sized>(&mut self, reader: &mut R) -> Result<u64> {
+        let mut reader = BodyReader {
+            reader,
+            offset: 0,
+            failed: false,
+        };
+        self.copy_raw(&mut reader).map_err(|err| {
+            if reader.failed {
+                self.body_error(reader.offset, err)
+            } else {
+                self.part_error(err)
+            }
+        })
+    }
+
+    /// Copies a part's body from a buffered `reader` without copying it through another buffer
+    /// first, returning its size.
+    ///
+    /// Errors from `reader` become [`FormDataError::PartBody`] errors.
+    fn copy_buffered<R: BufRead + ?Sized>(&mut self, reader: &mut R) -> Result<u64> {
+        let options = &self.options;
+        if options.cancellation.is_some()
+            || options.time_limit.is_some()
+            || options.rate_limit.is_some()
+        {
+            // pacing and cancellation need the chunked loop in `copy_raw`
+            return self.copy_body(reader);
+        }
+
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("a part header was just written");
+        let mut size = 0;
+        let mut failed = false;
+        let result = loop {
+            let data = match reader.fill_buf() {
+                Ok([]) => break Ok(size),
+                Ok(data) => data,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    failed = true;
+                    break Err(err);
+                }
+            };
+            if let Err(err) = writer.write_all(data) {
+                break Err(err);
+            }
+            #[cfg(feature = "manifest")]
+            if let Some(sha256) = &mut self.sha256 {
+                sha256.update(data);
+            }
+            let len = data.len();
+            reader.consume(len);
+            size += len as u64;
+        };
+        result.map_err(|err| {
+            if failed {
+                self.body_error(size, err)
+            } else {
+                self.part_error(err)
+            }
+        })
+    }
+
+    /// Wraps an error from reading the body of the current part, `offset` bytes in.
+    fn body_error(&self, offset: u64, err: Error) -> Error {
+        FormDataError::PartBody {
+            name: self
+                .parts
+                .last()
+                .map(|part| part.name.clone())
+                .unwrap_or_default(),
+            offset,
+            source: err,
+        }
+        .into()
+    }
+
+    /// Wraps an error that happened while writing the current part with its name.
+    pub(crate) fn part_error(&self, err: Error) -> Error {
+        match self.parts.last() {
+            Some(part) => FormDataError::in_part(&part.name, err),
+            None => err,
+        }
+    }
+
+    /// Opens the file at `path` for the part called `name`, following the file type policy. A
+    /// part that the policy leaves out is counted as done.
+    pub(crate) fn open_path(&mut self, name: &str, path: &Path) -> Result<Option<File>> {
+        let file = self.options.file_type_policy.open(path).map_err(|source| {
+            Error::from(FormDataError::Path {
+                name: name.to_owned(),
+                path: path.to_owned(),
+                source,
+            })
+        })?;
+        if file.is_none() {
+            self.skipped += 1;
+            self.progress.parts += 1;
+        }
+        Ok(file)
+    }
+
+    /// Copies a part's body from `reader`, returning its size. `reader` should be an in-memory
+    /// body that can't fail, or wrapped by [`FormData::copy_body`].
+    fn copy_raw<R: Read + ?Sized>(&mut self, reader: &mut R) -> Result<u64> {
+        let deadline = self.deadline();
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("a part header was just written");
+        let options = &self.options;
+        if options.cancellation.is_none()
+            && options.time_limit.is_none()
+            && options.rate_limit.is_none()
+        {
+            // `io::copy` knows platform-specific tricks that a plain loop doesn't
+            #[cfg(feature = "manifest")]
+            if let Some(sha256) = &mut self.sha256 {
+                return std::io::copy(reader, &mut HashingWriter { writer, sha256 });
+            }
+            return std::io::copy(reader, writer);
+        }
+
+        let mut buf = CopyBuf::new();
+        // keep chunks small enough that pacing is smooth at low rates
+        let chunk = match options.rate_limit {
+            Some(rate) => usize::try_from(rate.get())
+                .map_or(COPY_BUF_SIZE, |rate| rate.clamp(1, COPY_BUF_SIZE)),
+            None => COPY_BUF_SIZE,
+        };
+        let started = Instant::now();
+        let mut size = 0;
+        loop {
+            check_interrupted(options.cancellation.as_deref(), deadline)?;
+            let data = match buf.read(reader, chunk) {
+                Ok([]) => return Ok(size),
+                Ok(data) => data,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            writer.write_all(data)?;
+            #[cfg(feature = "manifest")]
+            if let Some(sha256) = &mut self.sha256 {
+                sha256.update(data);
+            }
+            size += data.len() as u64;
+
+            if let Some(rate) = options.rate_limit {
+                #[allow(clippy::cast_precision_loss)]
+                let due = Duration::from_secs_f64(size as f64 / rate.get() as f64);
+                if let Some(ahead) = due.checked_sub(started.elapsed()) {
+                    std::thread::sleep(ahead);
+                }
+            }
+        }
+    }
+
+    /// Writes part of a body that is being copied outside of [`FormData::copy_body`].
+    #[cfg(feature = "tokio")]
+    fn write_chunk(&mut self, data: &[u8]) -> Result<()> {
+        self.writer
+            .as_mut()
+            .expect("a part header was just written")
+            .write_all(data)?;
+        #[cfg(feature = "manifest")]
+        if let Some(sha256) = &mut self.sha256 {
+            sha256.update(data);
+        }
+        Ok(())
+    }
+
+    fn end_part(&mut self, size: u64) -> Result<()> {
+        let writer = self.writer.as_mut().expect("a part was just written");
+        let result = writer.write_all(b"\r\n").and_then(|()| {
+            if self.options.flush_per_part {
+                writer.flush()
+            } else {
+                Ok(())
+            }
+        });
+        result.map_err(|err| self.part_error(err))?;
+        self.written += size + 2;
+        self.progress = Progress {
+            parts: self.parts.len() + self.skipped,
+            bytes: self.written,
+        };
+        if let Some(summary) = self.parts.last_mut() {
+            summary.size = size;
+            #[cfg(feature = "manifest")]
+            {
+                summary.sha256 = self.sha256.take().map(manifest::hex);
+            }
+            debug!("wrote part {:?}: {} bytes", summary.name, size);
+            if let (Some(metrics), Some((_, started))) = (&self.metrics, self.timing) {
+                metrics.part_written(&summary.name, size, started.elapsed());
+            }
+        }
+        Ok(())
+    }
+
+    /// How many parts have been written in full, and where the last of them ended.
+    ///
+    /// After an error, this tells how far the document got: everything before
+    /// [`Progress::bytes`] was written intact, and the failed part and any after it were not.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # use std::io::{Error, ErrorKind, Read};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # struct Broken;
+    /// # impl Read for Broken {
+    /// #     fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+    /// #         Err(Error::new(ErrorKind::ConnectionReset, "the disk fell off"))
+    /// #     }
+    /// # }
+    /// let mut form = FormData::new(Vec::new());
+    /// form.write_field("cute", "yes")?;
+    /// if let Err(err) = form.write_file("corro", Broken, None, "image/svg+xml") {
+    ///     let progress = form.last_progress();
+    ///     eprintln!("{err} ({} parts, {} bytes sent)", progress.parts, progress.bytes);
+    ///     assert_eq!(progress.parts, 1);
+    /// }
+    /// # form.abort();
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn last_progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// The current settings.
+    #[must_use]
+    pub fn options(&self) -> &FormDataOptions {
+        &self.options
+    }
+
+    /// Replaces all of the settings at once. This is how to combine a fixed boundary from
+    /// [`FormData::with_boundary`] with [`FormDataOptions`].
+    pub fn set_options(&mut self, options: FormDataOptions) {
+        self.options = options;
+    }
+
+    /// Sets hooks that are called with the size and duration of each part, and of the whole
+    /// document when it is finished. See [`Metrics`] for an example.
+    pub fn set_metrics<M: Metrics + Send + Sync + 'static>(&mut self, metrics: M) {
+        self.metrics = Some(Arc::new(metrics));
+    }
+
+    /// Sets a flag that, once set to `true`, stops the document from being written.
+    ///
+    /// The flag is checked before each part and between each chunk of a part's body, so setting
+    /// it from another thread promptly interrupts even a very large [`FormData::write_path`]. The
+    /// interrupted method returns an error, and the document is left incomplete.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let cancelled = Arc::new(AtomicBool::new(false));
+    /// let mut form = FormData::new(Vec::new());
+    /// form.set_cancellation(cancelled.clone());
+    ///
+    /// cancelled.store(true, Ordering::Relaxed);
+    /// assert!(form.write_path("corro", "testdata/corro.svg", "image/svg+xml").is_err());
+    /// ```
+    pub fn set_cancellation(&mut self, cancelled: Arc<AtomicBool>) {
+        self.options.cancellation = Some(cancelled);
+    }
+
+    /// Limits how long writing the document may take, counting from when the first part starts.
+    ///
+    /// Like [`FormData::set_cancellation`], the limit is checked before each part and between
+    /// each chunk of a part's body; once it has passed, the interrupted method returns an error
+    /// of kind [`ErrorKind::TimedOut`]. A single blocked write to the writer is not interrupted.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    ///
+    /// let mut form = FormData::new(Vec::new());
+    /// form.set_time_limit(Duration::from_secs(30));
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_time_limit(&mut self, limit: Duration) {
+        self.options.time_limit = Some(limit);
+    }
+
+    /// Paces the copying of part bodies to at most `bytes_per_sec`, sleeping as needed.
+    ///
+    /// This is meant for tests and bandwidth-constrained uploaders; headers are not paced, and
+    /// each part's body is paced on its own.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::num::NonZeroU64;
+    ///
+    /// let mut form = FormData::new(Vec::new());
+    /// form.set_rate_limit(NonZeroU64::new(1024 * 1024).unwrap());
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_rate_limit(&mut self, bytes_per_sec: NonZeroU64) {
+        self.options.rate_limit = Some(bytes_per_sec);
+    }
+
+    /// Reads files for [`FormData::write_path`] and [`FormData::write_part`] on a background
+    /// thread, so that the next chunk is read from disk while the current one is being written.
+    ///
+    /// This overlaps disk and network latency for large uploads. It costs a thread per file and
+    /// is off by default.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormData::new(Vec::new());
+    /// form.set_read_ahead(true);
+    /// form.write_path("ferris", "testdata/rustacean-flat-noshadow.png", "image/png")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_read_ahead(&mut self, enabled: bool) {
+        self.options.read_ahead = enabled;
+    }
+
+    /// Flushes the writer after each part, so that a streaming consumer (such as a proxy)
+    /// sees each part as soon as it is complete rather than whenever a buffer fills up.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let stream = Vec::new();
+    /// let mut form = FormData::new(std::io::BufWriter::new(stream));
+    /// form.set_flush_per_part(true);
+    /// form.write_field("cute", "yes")?; // already sent to `stream`
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_flush_per_part(&mut self, enabled: bool) {
+        self.options.flush_per_part = enabled;
+    }
+
+    /// Adds `size` and `modification-date` parameters (see [`Part::with_size`] and
+    /// [`Part::with_modification_date`]) to parts read from files, taken from the file's metadata.
+    /// Parameters set explicitly on a [`Part`] are kept. [`FormData::reserve`] accounts for
+    /// the added parameters.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormData::new(Vec::new());
+    /// form.set_file_metadata(true);
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// let document = String::from_utf8(form.finish()?)?;
+    /// assert!(document.contains("; size=7576\r\n"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_file_metadata(&mut self, enabled: bool) {
+        self.options.file_metadata = enabled;
+    }
+
+    /// Converts lone `\n` and `\r` line breaks in non-file field values to `\r\n`, as
+    /// [RFC 7578 § 4.1][rfc7578sec4.1] asks for. Some servers expect this of multi-line values,
+    /// since browsers do it for `<textarea>` fields.
+    ///
+    /// This applies to [`FormData::write_field`], [`FormData::write_field_secret`], and
+    /// non-file [`Part`]s.
+    ///
+    /// [rfc7578sec4.1]: https://www.rfc-editor.org/rfc/rfc7578.html#section-4.1
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.set_normalize_newlines(true);
+    /// form.write_field("haiku", "crab walks sideways\nclaws click\n")?;
+    /// assert_eq!(
+    ///     form.describe(),
+    ///     "haiku: field \"crab walks sideways\\r\\nclaws click\\r\\n\", 34 bytes\n",
+    /// );
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_normalize_newlines(&mut self, enabled: bool) {
+        self.options.normalize_newlines = enabled;
+    }
+
+    /// Rejects part names that servers are likely to misread: empty names, names that are only
+    /// whitespace, and names containing `"` or control characters such as line breaks. By
+    /// default names are written as they are.
+    ///
+    /// A rejected name fails with a [`FormDataError::InvalidName`] error, of kind
+    /// [`ErrorKind::InvalidInput`][std::io::ErrorKind::InvalidInput], before anything is written
+    /// for the part.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, FormDataError};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.set_strict_names(true);
+    /// let err = form.write_field("say \"cute\"", "yes").unwrap_err();
+    /// assert!(matches!(
+    ///     err.get_ref().and_then(|err| err.downcast_ref()),
+    ///     Some(FormDataError::InvalidName { .. }),
+    /// ));
+    /// form.write_field("cute", "yes")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_strict_names(&mut self, enabled: bool) {
+        self.options.strict_names = enabled;
+    }
+
+    /// Sets the order of the headers in each part, for servers that only read some of them
+    /// correctly unless they come first. Headers that `order` leaves out follow it in the usual
+    /// order, which is `Content-Disposition`, then `Content-Type`, then any others.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, PartHeader};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormData::with_boundary(Vec::new(), "boundary")?;
+    /// form.set_header_order(&[PartHeader::ContentType]);
+    /// form.write_file("corro", &b"<svg/>"[..], Some("corro.svg".as_ref()), "image/svg+xml")?;
+    /// let document = String::from_utf8(form.finish()?)?;
+    /// assert!(document.starts_with(
+    ///     "--boundary\r\n\
+    ///      Content-Type: image/svg+xml\r\n\
+    ///      Content-Disposition: form-data; name=\"corro\"; filename=\"corro.svg\"\r\n",
+    /// ));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_header_order(&mut self, order: &[PartHeader]) {
+        self.options.header_order = order.to_vec();
+    }
+
+    /// Adds a final `application/json` part called `name` when the document is finished, listing
+    /// the parts before it, so that the receiver can check that it got all of them intact. Pass
+    /// `None` to stop adding it.
+    ///
+    /// The part's body is an array with an object for each part, in order, with the keys `name`,
+    /// `filename`, `content_type` (each a string or `null`), `size` (the length of its body), and
+    /// `sha256` (the SHA-256 hash of its body in lowercase hex, or `null` for a part written
+    /// before the manifest part was set).
+    /// [`FormData::content_length`] can't account for the part, and returns `None` while this is
+    /// set.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.set_manifest_part(Some("__manifest"));
+    /// form.write_field("cute", "yes")?;
+    /// let document = form.finish()?;
+    ///
+    /// let parts = form_data_builder::parse::FormDataParts::parse(&document, form.boundary())?;
+    /// assert_eq!(
+    ///     parts.get("__manifest").unwrap().body(),
+    ///     concat!(
+    ///         r#"[{"name":"cute","filename":null,"content_type":null,"size":3,"#,
+    ///         r#""sha256":"8a798890fe93817163b10b5f7bd2ca4d25d84c52739a645a889c173eee7d9d3d"}]"#,
+    ///     )
+    ///     .as_bytes(),
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "manifest")]
+    pub fn set_manifest_part(&mut self, name: Option<&str>) {
+        self.options.manifest_part = name.map(str::to_owned);
+    }
+
+    /// Sets how `filename` parameters that are not valid UTF-8 are written. By default they are
+    /// written lossily; see [`FilenameEncoding`] for the alternatives.
+    pub fn set_filename_encoding(&mut self, encoding: FilenameEncoding) {
+        self.options.filename_encoding = encoding;
+    }
+
+    /// Sets what [`FormData::write_part`] does with file parts whose bodies were already written
+    /// to the document, for batch uploads where the same attachment appears more than once. By
+    /// default every part is written; see [`DuplicatePolicy`] for the alternatives.
+    ///
+    /// ```
+    /// # use form_data_builder::{DuplicatePolicy, FormData, Part};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.set_duplicate_policy(DuplicatePolicy::Skip);
+    /// form.write_part(&Part::path("a", "testdata/corro.svg", "image/svg+xml"))?;
+    /// let corro = include_bytes!("../testdata/corro.svg");
+    /// form.write_part(&Part::file("b", corro, "image/svg+xml"))?;
+    /// assert_eq!(form.describe(), "a: file \"corro.svg\" (image/svg+xml), 7576 bytes\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Skipped parts aren't counted as parts, so don't use [`DuplicatePolicy::Skip`] with
+    /// [`FormDataTemplate::resume`], which counts parts to find where to carry on.
+    #[cfg(feature = "dedup")]
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.options.duplicate_policy = policy;
+    }
+
+    /// Adds a `Content-MD5` header ([RFC 1864][rfc1864]) with the MD5 digest of the body to each
+    /// file part written with [`FormData::write_part`], for storage gateways that check each part
+    /// on its own. Parts read from files are read twice: once to digest them, and once to write
+    /// them.
+    ///
+    /// [rfc1864]: https://www.rfc-editor.org/rfc/rfc1864.html
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, Part};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.set_content_md5(true);
+    /// form.write_part(&Part::file("cute", &b"yes"[..], "text/plain"))?;
+    /// let document = String::from_utf8(form.finish()?)?;
+    /// assert!(document.contains("Content-MD5: phBcCmEbQbCPEglQY1Anng==\r\n"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "content-md5")]
+    pub fn set_content_md5(&mut self, enabled: bool) {
+        self.options.content_md5 = enabled;
+    }
+
+    /// Sets how [`FormData::write_path`] and parts read from files (see [`Part::path`]) treat
+    /// paths that aren't regular files. By default they are opened like any other; see
+    /// [`FileTypePolicy`] for the alternatives.
+    ///
+    /// ```
+    /// # use form_data_builder::{FileTypePolicy, FormData};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.set_file_type_policy(FileTypePolicy::Skip);
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// # #[cfg(unix)]
+    /// form.write_path("null", "/dev/null", "application/octet-stream")?;
+    /// assert_eq!(form.describe(), "corro: file \"corro.svg\" (image/svg+xml), 7576 bytes\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_file_type_policy(&mut self, policy: FileTypePolicy) {
+        self.options.file_type_policy = policy;
+    }
+
+    /// Writes documents in a canonical form, so that a verifier that writes the same parts with the
+    /// same boundary and options gets the same bytes, for signing request bodies. In this form:
+    ///
+    /// - Headers are in the order `Content-Disposition`, `Content-Type`, then any others,
+    ///   whatever [`FormData::set_header_order`] says.
+    /// - Line breaks in non-file field values are converted to `\r\n`, as with
+    ///   [`FormData::set_normalize_newlines`]. Values streamed with
+    ///   [`FormData::write_field_reader`] or [`FormData::start_text_part`] can't be converted, so
+    ///   those fail in this form instead.
+    /// - `Content-Type`s are lowercased, except for parameter values, and their parameters are
+    ///   sorted by name, separated by `"; "`, and quoted only where they have to be.
+    ///
+    /// File bodies are written as they are. Options that add details from the file system, such
+    /// as [`FormData::set_file_metadata`], should be avoided unless the verifier sees the same
+    /// files.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let write = |content_type: &str, value: &str| -> std::io::Result<Vec<u8>> {
+    ///     let mut form = FormData::with_boundary(Vec::new(), "signed")?;
+    ///     form.set_canonical(true);
+    ///     form.write_file("doc", &b"{}"[..], None, content_type)?;
+    ///     form.write_field("note", value)?;
+    ///     form.finish()
+    /// };
+    /// assert_eq!(
+    ///     write("Application/JSON;version=2;charset=\"utf-8\"", "a\nb")?,
+    ///     write("application/json; charset=utf-8; version=2", "a\r\nb")?,
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_canonical(&mut self, enabled: bool) {
+        self.options.canonical = enabled;
+    }
+
+    fn normalizes_newlines(&self) -> bool {
+        self.options.normalize_newlines || self.options.canonical
+    }
+
+    /// Fails in the canonical form, since line breaks in a streamed field value aren't converted.
+    fn check_streamed_field(&self, name: &str) -> Result<()> {
+        if self.options.canonical {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("field {name:?}: a streamed value can't be written in the canonical form"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        let (started, _) = self.timing?;
+        Some(started + self.options.time_limit?)
+    }
+
+    /// Write a non-file field to the document.
+    ///
+    /// Names and values can be borrowed or owned strings, so neither is copied first.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_field("butts", "lol")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn write_field<'v, N: Into<Cow<'v, str>>, V: Into<Cow<'v, str>>>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> Result<()> {
+        self.write_text(&name.into(), value.into().as_bytes(), false)
+    }
+
+    /// Write a non-file field to the document if `value` is `Some`, and nothing if it is `None`.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let nickname: Option<String> = None;
+    /// form.write_field_opt("name", Some("ferris"))?;
+    /// form.write_field_opt("nickname", nickname)?;
+    /// assert_eq!(form.describe(), "name: field \"ferris\", 6 bytes\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormData::write_field`].
+    pub fn write_field_opt<'v, N: Into<Cow<'v, str>>, V: Into<Cow<'v, str>>>(
+        &mut self,
+        name: N,
+        value: Option<V>,
+    ) -> Result<()> {
+        match value {
+            Some(value) => self.write_field(name, value),
+            None => Ok(()),
+        }
+    }
+
+    /// Write a non-file field to the document, marking its value as sensitive.
+    ///
+    /// The field is sent as normal, but [`FormData::describe`] and the `Debug` output show
+    /// `[REDACTED]` in place of its value. The value is not retained after it is written.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_field_secret("token", "hunter2")?;
+    /// assert_eq!(form.describe(), "token: field [REDACTED]\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn write_field_secret<'v, N: Into<Cow<'v, str>>, V: Into<Cow<'v, str>>>(
+        &mut self,
+        name: N,
+        value: V,
+    ) -> Result<()> {
+        self.write_text(&name.into(), value.into().as_bytes(), true)
+    }
+
+    /// Write a non-file field with an empty value to the document, as browsers do for a text
+    /// input left blank.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_empty_field("nickname")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn write_empty_field<'n, N: Into<Cow<'n, str>>>(&mut self, name: N) -> Result<()> {
+        self.write_text(&name.into(), b"", false)
+    }
+
+    /// Write a non-file field to the document, copying its value from `reader`.
+    ///
+    /// This is for large text values, such as a generated report, that would otherwise have to
+    /// be collected into a `String` first. [`FormData::set_normalize_newlines`] does not apply to
+    /// values written this way, and they can't be written at all in the canonical form (see
+    /// [`FormData::set_canonical`]).
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let report = std::fs::File::open("testdata/corro.svg")?;
+    /// form.write_field_reader("report", report)?;
+    /// assert_eq!(form.describe(), "report: field, 7576 bytes\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if reading from `reader` fails, or
+    /// if the writer fails. Returns an error of kind [`ErrorKind::InvalidInput`] in the canonical
+    /// form.
+    pub fn write_field_reader<'n, N: Into<Cow<'n, str>>, R: Read>(
+        &mut self,
+        name: N,
+        mut reader: R,
+    ) -> Result<()> {
+        let name = name.into();
+        self.check_streamed_field(&name)?;
+        self.write_header(&name, None, None, &DispositionParams::default())?;
+        let size = self.copy_body(&mut reader)?;
+        self.end_part(size)
+    }
+
+    /// Write a boolean non-file field to the document, as `true` or `false`.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_bool("cute", true)?;
+    /// assert_eq!(form.describe(), "cute: field \"true\", 4 bytes\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn write_bool(&mut self, name: &str, value: bool) -> Result<()> {
+        self.write_field(name, if value { "true" } else { "false" })
+    }
+
+    /// Write an integer non-file field to the document, in decimal with a leading `-` if
+    /// negative and no leading zeros or `+`.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_int("legs", 10_u8)?;
+    /// form.write_int("offset", -3_i64)?;
+    /// form.write_int("count", [1, 2, 3].len())?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn write_int<I: Integer>(&mut self, name: &str, value: I) -> Result<()> {
+        self.write_field(name, value.to_string())
+    }
+
+    /// Write a floating-point non-file field to the document, in decimal without an exponent,
+    /// using the fewest digits that read back as the same value (`0.1`, `-2.5`, `1000000`).
+    /// Negative zero is written as `-0`.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_float("ratio", 0.1)?;
+    /// form.write_float("big", 1e21)?; // "1000000000000000000000"
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` is NaN or infinite, which have no canonical form, if `finish()`
+    /// has already been called or if the writer fails.
+    pub fn write_float(&mut self, name: &str, value: f64) -> Result<()> {
+        if !value.is_finite() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("field {name:?}: {value} is not a finite number"),
+            ));
+        }
+        self.write_field(name, value.to_string())
+    }
+
+    /// Write a date and time non-file field to the document in [RFC 3339][rfc3339] format, such
+    /// as `1997-02-12T16:29:51Z` or `1997-02-12T08:29:51-08:00`.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_datetime("taken_at", time::OffsetDateTime::now_utc())?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [rfc3339]: https://www.rfc-editor.org/rfc/rfc3339.html
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` can't be written in RFC 3339 format (its year is outside
+    /// 0000–9999, or its UTC offset has a seconds component), if `finish()` has already been
+    /// called or if the writer fails.
+    #[cfg(feature = "time")]
+    pub fn write_datetime(&mut self, name: &str, value: time::OffsetDateTime) -> Result<()> {
+        let value = value
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, format!("field {name:?}: {err}")))?;
+        self.write_field(name, &value)
+    }
+
+    /// Write a UUID non-file field to the document in its lowercase hyphenated form, such as
+    /// `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let id = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+    /// form.write_uuid("request_id", id)?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    #[cfg(feature = "uuid")]
+    pub fn write_uuid(&mut self, name: &str, id: uuid::Uuid) -> Result<()> {
+        let mut buf = uuid::Uuid::encode_buffer();
+        self.write_field(name, &*id.hyphenated().encode_lower(&mut buf))
+    }
+
+    /// Write a `text/csv` file field to the document, serializing `records` one row at a time
+    /// with a header row taken from the first record's field names, as bulk-import APIs expect.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// #[derive(serde::Serialize)]
+    /// struct Crab {
+    ///     name: &'static str,
+    ///     legs: u8,
+    /// }
+    ///
+    /// let crabs = [Crab { name: "ferris", legs: 10 }, Crab { name: "corro", legs: 8 }];
+    /// form.write_csv("crabs", Some("crabs.csv".as_ref()), &crabs)?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a record can't be written as CSV, if `finish()` has already been
+    /// called or if the writer fails. The part is then left unfinished, like a part whose body
+    /// failed to be read (see [`FormData::last_progress`]).
+    #[cfg(feature = "csv")]
+    pub fn write_csv<I>(&mut self, name: &str, filename: Option<&OsStr>, records: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: serde::Serialize,
+    {
+        let mut part = self.start_file_part(name, filename, "text/csv")?;
+        let mut csv = csv::Writer::from_writer(&mut part);
+        for record in records {
+            csv.serialize(record)?;
+        }
+        csv.flush()?;
+        drop(csv);
+        part.end()
+    }
+
+    /// Write an `application/x-ndjson` file field to the document, serializing `items` as
+    /// newline-delimited JSON, one line per item. Items are written as they are produced, so the
+    /// whole dataset is never held in memory.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let events = (1..=3).map(|n| serde_json::json!({ "id": n, "kind": "click" }));
+    /// form.write_ndjson("events", Some("events.ndjson".as_ref()), events)?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an item can't be serialized as JSON, if `finish()` has already been
+    /// called or if the writer fails. The part is then left unfinished, like a part whose body
+    /// failed to be read (see [`FormData::last_progress`]).
+    #[cfg(feature = "ndjson")]
+    pub fn write_ndjson<I>(&mut self, name: &str, filename: Option<&OsStr>, items: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: serde::Serialize,
+    {
+        let mut part = self.start_file_part(name, filename, "application/x-ndjson")?;
+        // serde_json writes a token at a time
+        let mut buffered = std::io::BufWriter::new(&mut part);
+        for item in items {
+            serde_json::to_writer(&mut buffered, &item)?;
+            buffered.write_all(b"\n")?;
+        }
+        buffered.flush()?;
+        drop(buffered);
+        part.end()
+    }
+
+    /// Write an `application/xml` file field to the document, serializing `value` with
+    /// [quick-xml], for SOAP-with-attachments and other XML endpoints. The root element is named
+    /// after `value`'s type.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// #[derive(serde::Serialize)]
+    /// struct Order {
+    ///     #[serde(rename = "@id")]
+    ///     id: u32,
+    ///     item: Vec<&'static str>,
+    /// }
+    ///
+    /// let order = Order { id: 7, item: vec!["crab", "ferris"] };
+    /// form.write_xml("order", &order)?;
+    /// // <Order id="7"><item>crab</item><item>ferris</item></Order>
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [quick-xml]: https://docs.rs/quick-xml
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` can't be represented as XML, in which case nothing is
+    /// written, if `finish()` has already been called or if the writer fails.
+    #[cfg(feature = "quick-xml")]
+    pub fn write_xml<T: serde::Serialize + ?Sized>(&mut self, name: &str, value: &T) -> Result<()> {
+        let xml = quick_xml::se::to_string(value)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, format!("field {name:?}: {err}")))?;
+        self.write_file(name, xml.as_bytes(), None, "application/xml")
+    }
+
+    /// Write `value` as non-file fields, one for each string, number, and `bool` in it, named
+    /// after where it is inside `value` the way `naming` says. This is how frameworks like Rails,
+    /// PHP, and Express's body parsers expect structured form data. Nulls, including `None`s,
+    /// are left out.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, NestedNaming};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// #[derive(serde::Serialize)]
+    /// struct Crab {
+    ///     name: &'static str,
+    ///     claws: Vec<u8>,
+    /// }
+    ///
+    /// let crab = Crab { name: "ferris", claws: vec![1, 2] };
+    /// form.write_nested("crab", &crab, NestedNaming::Brackets)?;
+    /// assert_eq!(
+    ///     form.describe(),
+    ///     "crab[name]: field \"ferris\", 6 bytes\n\
+    ///      crab[claws][0]: field \"1\", 1 bytes\n\
+    ///      crab[claws][1]: field \"2\", 1 bytes\n",
+    /// );
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` can't be serialized, such as because it is a map whose keys
+    /// aren't strings, in which case nothing is written, if `finish()` has already been called or
+    /// if the writer fails.
+    #[cfg(feature = "nested")]
+    pub fn write_nested<T: serde::Serialize + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &T,
+        naming: NestedNaming,
+    ) -> Result<()> {
+        let value = serde_json::to_value(value)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, format!("field {name:?}: {err}")))?;
+        for (name, value) in nested::flatten(&value, naming, &mut name.to_owned()) {
+            self.write_field(&name, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Write an `application/x-protobuf` file field to the document, encoding `message` with
+    /// [prost], for upload APIs that sit next to gRPC services.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// // usually a type generated by prost-build; `String` is `google.protobuf.StringValue`
+    /// let message = String::from("yes");
+    /// form.write_protobuf("cute", &message)?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [prost]: https://docs.rs/prost
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    #[cfg(feature = "prost")]
+    pub fn write_protobuf<M: prost::Message>(&mut self, name: &str, message: &M) -> Result<()> {
+        let body = message.encode_to_vec();
+        self.write_file(name, &body[..], None, "application/x-protobuf")
+    }
+
+    fn write_text(&mut self, name: &str, value: &[u8], sensitive: bool) -> Result<()> {
+        let value = if self.normalizes_newlines() {
+            normalize_newlines(value)
+        } else {
+            Cow::Borrowed(value)
+        };
+        let result = self.write_text_normalized(name, &value, sensitive);
+        #[cfg(feature = "zeroize")]
+        if let (true, Cow::Owned(mut value)) = (sensitive, value) {
+            zeroize::Zeroize::zeroize(&mut value);
+        }
+        result
+    }
+
+    fn write_text_normalized(&mut self, name: &str, value: &[u8], sensitive: bool) -> Result<()> {
+        self.write_header(name, None, None, &DispositionParams::default())?;
+        self.copy_raw(&mut &*value)
+            .map_err(|err| self.part_error(err))?;
+        if let Some(summary) = self.parts.last_mut().filter(|_| !sensitive) {
+            let preview = String::from_utf8_lossy(value);
+            summary.value = Some(
+                match preview.char_indices().nth(PartSummary::VALUE_PREVIEW) {
+                    Some((index, _)) => format!("{}...", &preview[..index]),
+                    None => preview.into_owned(),
+                },
+            );
+        }
+        if let Some(summary) = self.parts.last_mut() {
+            summary.sensitive = sensitive;
+        }
+        self.end_part(value.len() as u64)
+    }
+
+    /// Write a file field to the document, copying the data from `reader`.
+    ///
+    /// [RFC 7578 § 4.2](rfc7578sec4.2) advises "a name for the file SHOULD be supplied", but
+    /// "isn't mandatory for cases where the file name isn't availbale or is meaningless or
+    /// private".
+    ///
+    /// [rfc7578sec4.2]: https://www.rfc-editor.org/rfc/rfc7578.html#section-4.2
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// use std::io::Cursor;
+    ///
+    /// const CORRO: &[u8] = include_bytes!("../testdata/corro.svg");
+    /// form.write_file("corro", Cursor::new(CORRO), None, "image/svg+xml")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn write_file<R: Read>(
+        &mut self,
+        name: &str,
+        mut reader: R,
+        filename: Option<&OsStr>,
+        content_type: &str,
+    ) -> Result<()> {
+        self.write_header(
+            name,
+            filename,
+            Some(content_type),
+            &DispositionParams::default(),
+        )?;
+        let size = self.copy_body(&mut reader)?;
+        self.end_part(size)
+    }
+
+    /// Write a file field to the document like [`FormData::write_file`], copying the data
+    /// straight out of `reader`'s own buffer.
+    ///
+    /// Readers that already buffer their output, such as decoders and decompressors, don't need
+    /// the data copied through another buffer on its way to the writer.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// use std::io::BufReader;
+    ///
+    /// let reader = BufReader::new(std::fs::File::open("testdata/corro.svg")?);
+    /// form.write_file_buffered("corro", reader, Some("corro.svg".as_ref()), "image/svg+xml")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if `reader` fails, or if the
+    /// writer fails.
+    pub fn write_file_buffered<R: BufRead>(
+        &mut self,
+        name: &str,
+        mut reader: R,
+        filename: Option<&OsStr>,
+        content_type: &str,
+    ) -> Result<()> {
+        self.write_header(
+            name,
+            filename,
+            Some(content_type),
+            &DispositionParams::default(),
+        )?;
+        let size = self.copy_buffered(&mut reader)?;
+        self.end_part(size)
+    }
+
+    /// Write a file field to the document like [`FormData::write_file`], copying the data from
+    /// each of `sources` in turn.
+    ///
+    /// This puts a file back together from pieces, such as rotated log files or the chunks of a
+    /// split archive, without joining them first.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// use std::fs::File;
+    ///
+    /// let logs = [File::open("testdata/text-a.txt")?, File::open("testdata/text-b.txt")?];
+    /// form.write_file_concat("log", Some("app.log".as_ref()), "text/plain", logs)?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if a source fails, or if the
+    /// writer fails. [`FormDataError::PartBody`] errors count the offset from the start of the
+    /// first source.
+    pub fn write_file_concat<I>(
+        &mut self,
+        name: &str,
+        filename: Option<&OsStr>,
+        content_type: &str,
+        sources: I,
+    ) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Read,
+    {
+        let mut rest = sources.into_iter();
+        let mut reader = Concat {
+            current: rest.next(),
+            rest,
+        };
+        self.write_header(
+            name,
+            filename,
+            Some(content_type),
+            &DispositionParams::default(),
+        )?;
+        let size = self.copy_body(&mut reader)?;
+        self.end_part(size)
+    }
+
+    /// Write a file field with an empty body to the document.
+    ///
+    /// Browsers send a file input with no file selected as an empty part with an empty
+    /// `filename` parameter and a `Content-Type` of `application/octet-stream`, which some
+    /// servers expect for optional uploads:
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_empty_file("avatar", Some("".as_ref()), "application/octet-stream")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn write_empty_file(
+        &mut self,
+        name: &str,
+        filename: Option<&OsStr>,
+        content_type: &str,
+    ) -> Result<()> {
+        self.write_file(name, std::io::empty(), filename, content_type)
+    }
+
+    /// Write a file field to the document, opening the file at `path` and copying its data.
+    ///
+    /// This method detects the `filename` parameter from the `path`. To avoid this, use
+    /// [`FormData::write_file`].
+    ///
+    /// Paths that aren't regular files are handled according to
+    /// [`FormData::set_file_type_policy`], and may be skipped without writing anything.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if the file cannot be opened or
+    /// is rejected by the [`FileTypePolicy`], or if the writer fails.
+    ///
+    /// Paths that can't be used, such as directories and files without read permission, are
+    /// found before anything is written, and fail with a [`FormDataError::Path`] error:
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, FormDataError};
+    /// # let mut form = FormData::new(Vec::new());
+    /// let err = form.write_path("corro", "testdata", "image/svg+xml").unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "part \"corro\": failed to open testdata: is a directory",
+    /// );
+    /// match err.get_ref().and_then(|err| err.downcast_ref()) {
+    ///     Some(FormDataError::Path { name, path, .. }) => {
+    ///         assert_eq!((name.as_str(), path.to_str()), ("corro", Some("testdata")));
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// # form.finish().unwrap();
+    /// ```
+    pub fn write_path<P: AsRef<Path>>(
+        &mut self,
+        name: &str,
+        path: P,
+        content_type: &str,
+    ) -> Result<()> {
+        let Some(file) = self.open_path(name, path.as_ref())? else {
+            return Ok(());
+        };
+        let params = self.file_params(&file, DispositionParams::default())?;
+        self.write_header(name, path.as_ref().file_name(), Some(content_type), &params)?;
+        let size = self.copy_file(file)?;
+        self.end_part(size)
+    }
+
+    /// Write a file field for each of `paths`, all called `name`, like a browser submits an
+    /// `<input type="file" multiple>`. Each file's `Content-Type` is `content_type(path)`.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_files(
+    ///     "images",
+    ///     ["testdata/corro.svg", "testdata/rustacean-flat-noshadow.png"],
+    ///     |path| match path.extension().and_then(|extension| extension.to_str()) {
+    ///         Some("svg") => "image/svg+xml",
+    ///         _ => "image/png",
+    ///     },
+    /// )?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormData::write_path`], as soon as one of
+    /// the files fails. The error is a [`FormDataError::File`] saying which file it was, and the
+    /// files before it are left written.
+    pub fn write_files<I, P, F, S>(
+        &mut self,
+        name: &str,
+        paths: I,
+        mut content_type: F,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> S,
+        S: AsRef<str>,
+    {
+        for (index, path) in paths.into_iter().enumerate() {
+            let path = path.as_ref();
+            let content_type = content_type(path);
+            self.write_path(name, path, content_type.as_ref())
+                .map_err(|source| FormDataError::File {
+                    name: name.to_owned(),
+                    index,
+                    path: path.to_owned(),
+                    source,
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Write a file field to the document like [`FormData::write_path`], choosing the
+    /// `Content-Type` from the file's extension.
+    ///
+    /// Extensions are looked up in [`FormDataOptions::content_types`], then in a built-in table of
+    /// common types. Anything else is sent as `application/octet-stream`.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, FormDataOptions};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut options = FormDataOptions::default();
+    /// // an in-house format; `.svg` is in the built-in table
+    /// options
+    ///     .content_types
+    ///     .insert("corro".to_owned(), "application/x-corro".to_owned());
+    /// let mut form = FormData::with_options(Vec::new(), options);
+    /// form.write_path_auto("corro", "testdata/corro.svg")?;
+    /// assert_eq!(form.describe(), "corro: file \"corro.svg\" (image/svg+xml), 7576 bytes\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if the file cannot be opened, or
+    /// if the writer fails.
+    pub fn write_path_auto<P: AsRef<Path>>(&mut self, name: &str, path: P) -> Result<()> {
+        let content_type = self.options.content_type_for(path.as_ref()).to_owned();
+        self.write_path(name, path, &content_type)
+    }
+
+    /// Write a part to the document from a [`PartSource`], using the filename and content type it
+    /// suggests.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, ReaderSource};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let source = ReaderSource::new(std::fs::File::open("testdata/corro.svg")?)
+    ///     .with_filename("corro.svg");
+    /// form.write_source("corro", source)?;
+    /// assert_eq!(form.describe(), "corro: file \"corro.svg\" (image/svg+xml), 7576 bytes\n");
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if the source fails, or if the
+    /// writer fails.
+    pub fn write_source<S: PartSource>(&mut self, name: &str, mut source: S) -> Result<()> {
+        let (filename, content_type) = self.source_hints(source.filename(), source.content_type());
+        let mut reader = source.reader()?;
+        self.write_header(
+            name,
+            filename.as_deref(),
+            content_type.as_deref(),
+            &DispositionParams::default(),
+        )?;
+        let size = self.copy_body(&mut reader)?;
+        self.end_part(size)
+    }
+
+    /// Write a part to the document from an [`AsyncPartSource`], like [`FormData::write_source`].
+    ///
+    /// The writer is still written to synchronously, so it should be one that doesn't block, such
+    /// as a `Vec<u8>` or a [`SpooledWriter`][crate::SpooledWriter].
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, ReaderSource};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let source = ReaderSource::new(&b"<svg/>"[..]).with_filename("corro.svg");
+    /// form.write_source_async("corro", source).await?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if the source fails, or if the
+    /// writer fails.
+    #[cfg(feature = "tokio")]
+    pub async fn write_source_async<S: AsyncPartSource>(
+        &mut self,
+        name: &str,
+        mut source: S,
+    ) -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let (filename, content_type) = self.source_hints(source.filename(), source.content_type());
+        let mut reader = source.reader().await?;
+        self.write_header(
+            name,
+            filename.as_deref(),
+            content_type.as_deref(),
+            &DispositionParams::default(),
+        )?;
+
+        let deadline = self.deadline();
+        let chunk = match self.options.rate_limit {
+            Some(rate) => usize::try_from(rate.get())
+                .map_or(COPY_BUF_SIZE, |rate| rate.clamp(1, COPY_BUF_SIZE)),
+            None => COPY_BUF_SIZE,
+        };
+        let mut buf = vec![0; chunk];
+        let started = Instant::now();
+        let mut size = 0;
+        loop {
+            check_interrupted(self.options.cancellation.as_deref(), deadline)
+                .map_err(|err| self.part_error(err))?;
+            let n = match reader.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    return Err(FormDataError::PartBody {
+                        name: name.to_owned(),
+                        offset: size,
+                        source: err,
+                    }
+                    .into())
+                }
+            };
+            self.write_chunk(&buf[..n])
+                .map_err(|err| self.part_error(err))?;
+            size += n as u64;
+
+            if let Some(rate) = self.options.rate_limit {
+                #[allow(clippy::cast_precision_loss)]
+                let due = Duration::from_secs_f64(size as f64 / rate.get() as f64);
+                if let Some(ahead) = due.checked_sub(started.elapsed()) {
+                    tokio::time::sleep(ahead).await;
+                }
+            }
+        }
+        self.end_part(size)
+    }
+
+    /// The filename and content type [`FormData::write_source`] sends for a source with these
+    /// hints.
+    fn source_hints(
+        &self,
+        filename: Option<&OsStr>,
+        content_type: Option<&str>,
+    ) -> (Option<OsString>, Option<String>) {
+        let filename = filename.map(OsStr::to_owned);
+        let content_type = match (content_type, &filename) {
+            (Some(content_type), _) => Some(content_type.to_owned()),
+            (None, Some(filename)) => Some(
+                self.options
+                    .content_type_for(Path::new(filename))
+                    .to_owned(),
+            ),
+            (None, None) => None,
+        };
+        (filename, content_type)
+    }
+
+    /// The total length of the document, including anything already written, if `sources` are
+    /// written next with [`FormData::write_source`] and then the document is finished. This is the
+    /// `Content-Length` to send ahead of a streamed document.
+    ///
+    /// Returns `None` if any source's [`PartSource::len_hint`] is `None`, if a filename can't be
+    /// encoded, if the document is already finished, if a manifest part will be added (see
+    /// [`FormData::set_manifest_part`]), or if the document is compressed (see
+    /// [`FormData::gzip`]).
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # use std::path::PathBuf;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let sources = [
+    ///     ("corro", PathBuf::from("testdata/corro.svg")),
+    ///     ("ferris", PathBuf::from("testdata/rustacean-flat-noshadow.png")),
+    /// ];
+    /// let mut form = FormData::new(Vec::new());
+    /// let content_length = form.content_length(sources.iter().map(|(n, s)| (*n, s)));
+    /// for (name, source) in sources {
+    ///     form.write_source(name, source)?;
+    /// }
+    /// assert_eq!(content_length, Some(form.finish()?.len() as u64));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_length<'a, S, I>(&self, sources: I) -> Option<u64>
+    where
+        S: PartSource + 'a,
+        I: IntoIterator<Item = (&'a str, &'a S)>,
+    {
+        self.writer.as_ref()?;
+        #[cfg(feature = "manifest")]
+        if self.options.manifest_part.is_some() {
+            return None;
+        }
+        if self.content_encoding.is_some() {
+            return None;
+        }
+        let mut len = self.written + 2 + self.boundary.len() as u64 + 4;
+        let mut header = Vec::new();
+        for (name, source) in sources {
+            let body = source.len_hint()?;
+            let (filename, content_type) =
+                self.source_hints(source.filename(), source.content_type());
+            self.render_header(
+                &mut header,
+                name,
+                filename.as_deref(),
+                content_type.as_deref(),
+                &DispositionParams::default(),
+            )
+            .ok()?;
+            len += header.len() as u64 + body + 2;
+        }
+        Some(len)
+    }
+
+    /// Starts a part, returning a [`PartWriter`] that its body can be written to.
+    ///
+    /// This is for generating a body bit by bit, without a reader to copy it from. Leaving out
+    /// both `filename` and `content_type` makes a non-file field.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// use std::io::Write;
+    ///
+    /// let filename = Some("squares.txt".as_ref());
+    /// let mut part = form.start_part("squares", filename, Some("text/plain"))?;
+    /// for i in 1..=10 {
+    ///     part.write_all(format!("{}\n", i * i).as_bytes())?;
+    /// }
+    /// part.end()?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn start_part(
+        &mut self,
+        name: &str,
+        filename: Option<&OsStr>,
+        content_type: Option<&str>,
+    ) -> Result<PartWriter<'_, W>> {
+        if filename.is_none() && content_type.is_none() {
+            self.check_streamed_field(name)?;
+        }
+        self.write_header(name, filename, content_type, &DispositionParams::default())?;
+        Ok(PartWriter::new(self))
+    }
+
+    /// Starts a non-file field, returning a [`PartWriter`] that its value can be written to.
+    ///
+    /// Like [`FormData::write_field_reader`], this can't be used in the canonical form (see
+    /// [`FormData::set_canonical`]).
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let mut part = form.start_text_part("cute")?;
+    /// write!(part, "{}", "yes")?;
+    /// part.end()?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails. Returns an
+    /// error of kind [`ErrorKind::InvalidInput`] in the canonical form.
+    pub fn start_text_part(&mut self, name: &str) -> Result<PartWriter<'_, W>> {
+        self.start_part(name, None, None)
+    }
+
+    /// Starts a file field, returning a [`PartWriter`] that its contents can be written to.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// let mut part = form.start_file_part("log", Some("build.log".as_ref()), "text/plain")?;
+    /// writeln!(part, "build finished")?;
+    /// part.end()?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn start_file_part(
+        &mut self,
+        name: &str,
+        filename: Option<&OsStr>,
+        content_type: &str,
+    ) -> Result<PartWriter<'_, W>> {
+        self.start_part(name, filename, Some(content_type))
+    }
+
+    /// Write a part with a header block of the caller's choosing, copying its body from `reader`.
+    ///
+    /// `headers` is written as it is after the boundary line, so it must be complete header
+    /// lines ending with the blank line that separates them from the body (`\r\n\r\n`). Nothing
+    /// else about it is checked. This is for servers that need headers or an ordering that the
+    /// other methods can't produce.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_raw_part(
+    ///     b"Content-Type: text/plain\r\n\
+    ///       Content-Disposition: form-data; name=\"cute\"\r\n\
+    ///       X-Cuteness: 11\r\n\
+    ///       \r\n",
+    ///     &b"yes"[..],
+    /// )?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidInput`] if `headers` doesn't end with
+    /// `\r\n\r\n`, or an error if `finish()` has already been called or if the writer fails.
+    pub fn write_raw_part<R: Read>(&mut self, headers: &[u8], mut reader: R) -> Result<()> {
+        if !headers.ends_with(b"\r\n\r\n") {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "part headers must end with a blank line",
+            ));
+        }
+        // best effort, for `describe()` and logging
+        let parsed = parse::parse_part(&headers[..headers.len() - 4], &[], false).ok();
+        let mut summary = PartSummary {
+            name: parsed
+                .as_ref()
+                .map_or_else(|| "[raw]".to_owned(), |part| part.name().to_owned()),
+            filename: parsed
+                .as_ref()
+                .and_then(|part| part.filename().map(str::to_owned)),
+            content_type: parsed
+                .as_ref()
+                .and_then(|part| part.content_type().map(str::to_owned)),
+            value: None,
+            sensitive: false,
+            size: 0,
+            offset: 0,
+            #[cfg(feature = "manifest")]
+            sha256: None,
+        };
+        self.write_raw_header(&summary.name, headers)?;
+        summary.offset = self.written;
+        self.parts.push(summary);
+        #[cfg(feature = "manifest")]
+        {
+            self.sha256 = self.options.manifest_part.is_some().then(Sha256::new);
+        }
+
+        let size = self.copy_body(&mut reader)?;
+        self.end_part(size)
+    }
+
+    /// Write a field described by a [`Part`] to the document.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, Part};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_part(&Part::text("cute", "yes"))?;
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called, if the part is read from a file
+    /// that cannot be opened, or if the writer fails. An error opening the file keeps its
+    /// [`ErrorKind`] and names the part and path in its message.
+    pub fn write_part(&mut self, part: &Part<'_>) -> Result<()> {
+        #[cfg_attr(
+            not(any(feature = "dedup", feature = "content-md5")),
+            allow(unused_mut)
+        )]
+        let mut file = match part.file_path() {
+            Some(path) => match self.open_path(part.name(), path)? {
+                Some(file) => Some(file),
+                None => return Ok(()),
+            },
+            None => None,
+        };
+        if let (true, Some(body)) = (part.is_text(), part.body()) {
+            return self.write_text(part.name(), body, part.is_sensitive());
+        }
+        // with both on, each file is read once for both hashes
+        #[cfg(all(feature = "dedup", feature = "content-md5"))]
+        let (sha256, md5) =
+            if self.options.duplicate_policy != DuplicatePolicy::Keep && self.options.content_md5 {
+                let (sha256, md5) =
+                    hash::hash_file_pair::<Sha256, md5::Md5>(part.body(), file.as_mut())
+                        .map_err(|err| FormDataError::in_part(part.name(), err))?;
+                (Some(sha256), Some(md5))
+            } else {
+                (None, None)
+            };
+        #[cfg(all(feature = "dedup", not(feature = "content-md5")))]
+        let sha256 = None;
+        #[cfg(all(feature = "content-md5", not(feature = "dedup")))]
+        let md5 = None;
+        #[cfg(feature = "dedup")]
+        let duplicate = self.find_duplicate(part, file.as_mut(), sha256)?;
+        #[cfg(not(feature = "dedup"))]
+        let duplicate = false;
+        #[cfg(feature = "dedup")]
+        if duplicate && self.options.duplicate_policy == DuplicatePolicy::Skip {
+            debug!("skipped duplicate part {:?}", part.name());
+            return Ok(());
+        }
+        #[cfg(feature = "content-md5")]
+        if self.options.content_md5 {
+            use md5::Digest;
+
+            let md5 = match md5 {
+                // duplicates are written with an empty body
+                _ if duplicate => md5::Md5::digest([]),
+                Some(md5) => md5,
+                None => hash::hash_file::<md5::Md5>(part.body(), file.as_mut())
+                    .map_err(|err| FormDataError::in_part(part.name(), err))?,
+            };
+            self.content_md5 = Some(content_md5::content_md5(&md5));
+        }
+
+        let params = match &file {
+            Some(file) => self.file_params(file, *part.disposition())?,
+            None => *part.disposition(),
+        };
+        self.write_header(
+            part.name(),
+            part.filename().map(OsStr::new),
+            part.content_type(),
+            &params,
+        )?;
+        if duplicate {
+            return self.end_part(0);
+        }
+        let size = match (file, part.body()) {
+            (Some(file), _) => self.copy_file(file)?,
+            (None, body) => self
+                .copy_raw(&mut body.unwrap_or_default())
+                .map_err(|err| self.part_error(err))?,
+        };
+        self.end_part(size)
+    }
+
+    /// Hashes the body of a non-text part under a [`DuplicatePolicy`] other than `Keep`, unless
+    /// `sha256` is its hash already, returning whether the same body was already written, and
+    /// sets its `Content-ID` if asked to.
+    #[cfg(feature = "dedup")]
+    fn find_duplicate(
+        &mut self,
+        part: &Part<'_>,
+        file: Option<&mut File>,
+        sha256: Option<sha2::digest::Output<Sha256>>,
+    ) -> Result<bool> {
+        let policy = self.options.duplicate_policy;
+        if policy == DuplicatePolicy::Keep {
+            return Ok(false);
+        }
+        let digest: [u8; 32] = match sha256 {
+            Some(sha256) => sha256,
+            None => hash::hash_file::<Sha256>(part.body(), file)
+                .map_err(|err| FormDataError::in_part(part.name(), err))?,
+        }
+        .into();
+        let duplicate = self.digests.iter().any(|(_, seen)| *seen == digest);
+        if !duplicate {
+            self.digests.push((self.parts.len(), digest));
+        }
+        if policy == DuplicatePolicy::ContentId {
+            self.content_id = Some(dedup::content_id(&digest));
+        }
+        Ok(duplicate)
+    }
+
+    /// Hashes the body of a part that [`FormData::find_duplicate`] would hash, for
+    /// [`FormData::predicted_len`]. Bodies that can't be read aren't hashed.
+    #[cfg(feature = "dedup")]
+    fn predicted_digest(&self, part: &Part<'_>) -> Option<[u8; 32]> {
+        if self.options.duplicate_policy == DuplicatePolicy::Keep
+            || (part.is_text() && part.body().is_some())
+        {
+            return None;
+        }
+        let mut file = match part.file_path() {
+            Some(path) => Some(File::open(path).ok()?),
+            None => None,
+        };
+        let digest = hash::hash_file::<Sha256>(part.body(), file.as_mut()).ok()?;
+        Some(digest.into())
+    }
+
+    /// Summarizes the parts written so far, one per line. File contents are never shown, and the
+    /// values of fields written with [`FormData::write_field_secret`] are redacted. This is
+    /// suitable for logging what is about to be uploaded.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let mut form = FormData::new(Vec::new());
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// form.write_field("cute", "yes")?;
+    /// assert_eq!(
+    ///     form.describe(),
+    ///     "corro: file \"corro.svg\" (image/svg+xml), 7576 bytes\n\
+    ///      cute: field \"yes\", 3 bytes\n",
+    /// );
+    /// # form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn describe(&self) -> String {
+        use std::fmt::Write as _;
+
+        let mut out = String::new();
+        for part in &self.parts {
+            out.push_str(&part.name);
+            if part.filename.is_none() && part.content_type.is_none() {
+                out.push_str(": field");
+            } else {
+                out.push_str(": file");
+                if let Some(filename) = &part.filename {
+                    let _ = write!(out, " {filename:?}");
+                }
+                if let Some(content_type) = &part.content_type {
+                    let _ = write!(out, " ({content_type})");
+                }
+            }
+            match part.value() {
+                Some(value) if part.sensitive => {
+                    let _ = writeln!(out, " {value}");
+                }
+                Some(value) => {
+                    let _ = writeln!(out, " {value:?}, {} bytes", part.size);
+                }
+                None => {
+                    let _ = writeln!(out, ", {} bytes", part.size);
+                }
+            }
+        }
+        out
+    }
+
+    /// Lists the parts written so far, with where each one's body is in the document. Unlike
+    /// [`FormData::describe`], this is meant for programs, such as for auditing an upload or
+    /// comparing it with what the server says it received.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormData::new(Vec::new());
+    /// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// form.write_field("cute", "yes")?;
+    /// let document = form.finish()?;
+    ///
+    /// let manifest = form.manifest();
+    /// assert_eq!(manifest[0].filename.as_deref(), Some("corro.svg"));
+    /// let cute = &manifest[1];
+    /// let start = usize::try_from(cute.offset)?;
+    /// assert_eq!(&document[start..][..3], b"yes");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn manifest(&self) -> Vec<ManifestEntry> {
+        self.parts
+            .iter()
+            .map(|part| ManifestEntry {
+                name: part.name.clone(),
+                filename: part.filename.clone(),
+                content_type: part.content_type.clone(),
+                size: part.size,
+                offset: part.offset,
+                #[cfg(feature = "manifest")]
+                sha256: part.sha256.clone(),
+            })
+            .collect()
+    }
+
+    /// Returns the boundary that separates the parts of the document.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let form = FormData::with_boundary(Vec::new(), "boundary")?;
+    /// assert_eq!(form.boundary(), "boundary");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Returns the value of the `Content-Type` header that corresponds with the document.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # struct Request;
+    /// # impl Request {
+    /// #     fn with_header(&mut self, key: &str, value: String) {}
+    /// # }
+    /// # let mut request = Request;
+    /// # let mut form = FormData::new(Vec::new());
+    /// // your HTTP client's API may vary
+    /// request.with_header("Content-Type", form.content_type_header());
+    /// ```
+    ///
+    /// The boundary is quoted if it contains characters that aren't allowed in a bare parameter
+    /// value, which only happens with some boundaries passed to [`FormData::with_boundary`].
+    pub fn content_type_header(&self) -> String {
+        const PREFIX: &str = "multipart/form-data; boundary=";
+        let quote = !parse::is_token(&self.boundary);
+        let mut header = String::with_capacity(PREFIX.len() + self.boundary.len() + 2);
+        header.push_str(PREFIX);
+        if quote {
+            header.push('"');
+        }
+        header.push_str(&self.boundary);
+        if quote {
+            header.push('"');
+        }
+        header
+    }
+
+    /// Returns the request headers that go with the document, as `(name, value)` pairs.
+    ///
+    /// This is always `Content-Type`, plus `Content-Length` once the document is finished and its
+    /// length is known, and `Content-Encoding` if the document is compressed (see
+    /// [`FormData::gzip`]).
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # struct Request;
+    /// # impl Request {
+    /// #     fn with_header(&mut self, key: &str, value: String) {}
+    /// # }
+    /// # let mut request = Request;
+    /// let mut form = FormData::with_boundary(Vec::new(), "boundary")?;
+    /// form.write_field("cute", "yes")?;
+    /// let document = form.finish()?;
+    ///
+    /// let headers = form.headers();
+    /// assert_eq!(headers[1], ("Content-Length", document.len().to_string()));
+    /// for (name, value) in headers {
+    ///     // your HTTP client's API may vary
+    ///     request.with_header(name, value);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![("Content-Type", self.content_type_header())];
+        if let Some(encoding) = self.content_encoding {
+            headers.push(("Content-Encoding", encoding.to_owned()));
+        } else if self.finished {
+            headers.push(("Content-Length", self.written.to_string()));
+        }
+        headers
+    }
+
+    /// Returns the value of the `Content-Type` header as an [`http::HeaderValue`], for HTTP
+    /// clients built on the `http` crate.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// let form = FormData::new(Vec::new());
+    /// let request = http::Request::post("https://example.com/upload")
+    ///     .header(http::header::CONTENT_TYPE, form.content_type_header_value());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Does not panic: every valid boundary makes a valid header value.
+    #[cfg(feature = "http")]
+    #[must_use]
+    pub fn content_type_header_value(&self) -> http::HeaderValue {
+        http::HeaderValue::try_from(self.content_type_header())
+            .expect("a valid boundary should make a valid header value")
+    }
+
+    /// Returns the media type of the document, with its `boundary` parameter, for HTTP clients
+    /// that take a [`mime::Mime`].
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let form = FormData::with_boundary(Vec::new(), "boundary")?;
+    /// let content_type = form.content_type();
+    /// assert_eq!(content_type.essence_str(), "multipart/form-data");
+    /// assert_eq!(content_type.get_param(mime::BOUNDARY).unwrap(), "boundary");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Does not panic: every valid boundary makes a valid media type.
+    #[cfg(feature = "mime")]
+    #[must_use]
+    pub fn content_type(&self) -> mime::Mime {
+        self.content_type_header()
+            .parse()
+            .expect("a valid boundary should make a valid media type")
+    }
+
+    /// The number of bytes that writing `parts` and finishing will add to the document.
+    fn predicted_len<'p, 'a: 'p>(&self, parts: impl IntoIterator<Item = &'p Part<'a>>) -> u64 {
+        const DISPOSITION: &str = "\r\nContent-Disposition: form-data; name=\"\"\r\n\r\n";
+
+        let boundary = self.boundary.len() as u64;
+        let mut len = 2 + boundary + 4;
+        #[cfg(feature = "manifest")]
+        let mut manifest = self.options.manifest_part.as_ref().map(|_| self.manifest());
+        #[cfg(feature = "dedup")]
+        let mut seen: Vec<[u8; 32]> = self.digests.iter().map(|(_, digest)| *digest).collect();
+        for part in parts {
+            let mut params = *part.disposition();
+            #[cfg_attr(not(feature = "dedup"), allow(unused_mut))]
+            let mut body = match (part.body(), part.file_path()) {
+                (Some(body), _) if part.is_text() && self.normalizes_newlines() => {
+                    normalize_newlines(body).len() as u64
+                }
+                (Some(body), _) => body.len() as u64,
+                (None, Some(path)) => match std::fs::metadata(path) {
+                    Ok(metadata) => {
+                        if self.options.file_metadata {
+                            params = params.or_metadata(&metadata);
+                        }
+                        metadata.len()
+                    }
+                    // the builder reports missing files, but the rest of the part still counts
+                    Err(_) => 0,
+                },
+                (None, None) => 0,
+            };
+            #[cfg(feature = "dedup")]
+            if let Some(digest) = self.predicted_digest(part) {
+                let policy = self.options.duplicate_policy;
+                if seen.contains(&digest) {
+                    if policy == DuplicatePolicy::Skip {
+                        continue;
+                    }
+                    body = 0;
+                } else {
+                    seen.push(digest);
+                }
+                if policy == DuplicatePolicy::ContentId {
+                    len +=
+                        "Content-ID: \r\n".len() as u64 + dedup::content_id(&digest).len() as u64;
+                }
+            }
+            len += 2 + boundary + DISPOSITION.len() as u64 + part.name().len() as u64;
+            let mut buf = Vec::new();
+            if let Some(filename) = part.filename() {
+                buf.extend_from_slice(b"; filename=\"\"");
+                // a filename that can't be encoded fails the part, so any length will do
+                let _ = self
+                    .options
+                    .filename_encoding
+                    .encode(OsStr::new(filename), &mut buf);
+            }
+            params.write(&mut buf);
+            len += buf.len() as u64;
+            #[cfg(feature = "content-md5")]
+            if self.options.content_md5 && !part.is_text() {
+                len += "Content-MD5: \r\n".len() as u64 + 24;
+            }
+            if let Some(content_type) = part.content_type() {
+                let content_type = if self.options.canonical {
+                    parse::canonical_content_type(content_type).len()
+                } else {
+                    content_type.len()
+                };
+                len += "Content-Type: \r\n".len() as u64 + content_type as u64;
+            }
+            len += body + 2;
+            #[cfg(feature = "manifest")]
+            if let Some(manifest) = &mut manifest {
+                manifest.push(ManifestEntry {
+                    name: part.name().to_owned(),
+                    filename: part.filename().map(str::to_owned),
+                    content_type: part.content_type().map(str::to_owned),
+                    size: body,
+                    offset: 0,
+                    // only the length of the hash matters
+                    sha256: Some("0".repeat(64)),
+                });
+            }
+        }
+        #[cfg(feature = "manifest")]
+        if let (Some(name), Some(manifest)) = (&self.options.manifest_part, manifest) {
+            let json = manifest::to_json(&manifest);
+            len += 2 + boundary + DISPOSITION.len() as u64 + name.len() as u64;
+            len += "Content-Type: application/json\r\n".len() as u64 + json.len() as u64 + 2;
+        }
+        len
+    }
+}
+
+impl<W: Write + Seek + Truncate> FormData<W> {
+    /// Discards the part that was being written when an error happened, so that writing can carry
+    /// on from the last complete part (see [`FormData::last_progress`]) instead of starting over.
+    ///
+    /// The writer is truncated to the end of the last complete part and positioned there. It
+    /// should hold the document from its start, such as a staging file. Parts after that must be
+    /// written again, starting with the failed one. If nothing failed, this does nothing.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # use std::io::{Cursor, Error, ErrorKind, Read};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # struct Flaky(bool);
+    /// # impl Read for Flaky {
+    /// #     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    /// #         if std::mem::replace(&mut self.0, true) {
+    /// #             Ok(0)
+    /// #         } else {
+    /// #             Err(Error::new(ErrorKind::ConnectionReset, "the disk fell off"))
+    /// #         }
+    /// #     }
+    /// # }
+    /// # let mut flaky = Flaky(false);
+    /// let mut form = FormData::new(Cursor::new(Vec::new()));
+    /// form.write_field("cute", "yes")?;
+    /// while let Err(err) = form.write_file("corro", &mut flaky, None, "image/svg+xml") {
+    ///     eprintln!("retrying: {err}");
+    ///     form.resume()?;
+    /// }
+    /// let document = form.finish()?.into_inner();
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document was finished or aborted, or if seeking or truncating the
+    /// writer fails.
+    pub fn resume(&mut self) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| Error::other("this method cannot be used after using `finish()`"))?;
+        let Progress { parts, bytes } = self.progress;
+        writer.truncate(bytes)?;
+        writer.seek(SeekFrom::Start(bytes))?;
+        if self.parts.len() > parts {
+            debug!(
+                "resuming multipart/form-data document after {} parts, {} bytes",
+                parts, bytes
+            );
+        }
+        let written = parts - self.skipped;
+        self.parts.truncate(written);
+        #[cfg(feature = "dedup")]
+        self.digests.retain(|&(before, _)| before < written);
+        self.written = bytes;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> FormData<W> {
+    /// Finishes the document like [`FormData::finish`], then flushes the writer and seeks it back
+    /// to where the document starts, returning it along with the document's length.
+    ///
+    /// This is for staging a document whose parts' sizes aren't known up front, such as ones
+    /// written with [`FormData::start_part`], so that it can be sent with a `Content-Length`
+    /// header. [`FormData::headers`] also includes the length from now on.
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # use std::io::Write;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // or a staging file
+    /// let mut form = FormData::new(std::io::Cursor::new(Vec::new()));
+    /// let mut part = form.start_text_part("log")?;
+    /// writeln!(part, "no idea how long this will be")?;
+    /// part.end()?;
+    /// let (staged, len) = form.finish_staged()?;
+    /// assert_eq!(staged.position(), 0);
+    /// assert_eq!(staged.get_ref().len() as u64, len);
+    /// // ... send `len` bytes from `staged` ...
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if finishing the document fails, if flushing or seeking the writer fails,
+    /// or if the writer is positioned before the end of the document, such as when it was moved
+    /// outside of this builder.
+    pub fn finish_staged(&mut self) -> Result<(W, u64)> {
+        let mut writer = self.finish()?;
+        writer.flush()?;
+        let end = writer.stream_position()?;
+        let start = end.checked_sub(self.written).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "the writer is positioned before the end of the document",
+            )
+        })?;
+        writer.seek(SeekFrom::Start(start))?;
+        Ok((writer, self.written))
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<W: Write> FormData<flate2::write::GzEncoder<W>> {
+    /// Finishes the document like [`FormData::finish`], then finishes compressing it, returning
+    /// the underlying writer. See [`FormData::gzip`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn finish_gzip(&mut self) -> Result<W> {
+        self.finish()?.finish()
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl<W: Write> FormData<Tee<W, HmacWriter>> {
+    /// Writes the signature field and finishes the document like [`FormData::finish`], returning
+    /// the underlying writer. See [`FormData::sign_hmac`].
+    ///
+    /// A manifest part (see [`FormData::set_manifest_part`]) is written after the signature
+    /// field, and isn't covered by it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `finish()` has already been called or if the writer fails.
+    pub fn finish_signed(&mut self) -> Result<W> {
+        let signer = self
+            .writer
+            .as_ref()
+            .ok_or_else(|| Error::other("you can only finish once"))?
+            .second();
+        let (name, signature) = (signer.field().to_owned(), signer.hex());
+        self.write_field(&name, &signature)?;
+        Ok(self.finish()?.into_inner().0)
+    }
+}
+
+impl<W: Write + Reserve> FormData<W> {
+    /// Reserves room for `parts` and the closing delimiter, so that writing them doesn't
+    /// reallocate. Parts read from files are sized from their metadata, and are left out if that
+    /// can't be read.
+    ///
+    /// This works for any in-memory writer that implements [`Reserve`], such as a `Vec<u8>` or a
+    /// [`bytes::BytesMut`] (with the `bytes` feature).
+    ///
+    /// ```
+    /// # use form_data_builder::{FormDataBuf, Part};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let parts = [
+    ///     Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+    ///     Part::text("cute", "yes"),
+    /// ];
+    /// let mut form = FormDataBuf::with_capacity(0);
+    /// form.reserve(&parts);
+    /// for part in &parts {
+    ///     form.write_part(part)?;
+    /// }
+    /// let document = form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reserve(&mut self, parts: &[Part<'_>]) {
+        self.reserve_for(parts);
+    }
+
+    /// Like [`FormData::reserve`], for parts that aren't in a slice.
+    pub(crate) fn reserve_for<'p, 'a: 'p>(
+        &mut self,
+        parts: impl IntoIterator<Item = &'p Part<'a>>,
+    ) {
+        let additional = usize::try_from(self.predicted_len(parts)).unwrap_or(usize::MAX);
+        if let Some(writer) = &mut self.writer {
+            writer.reserve(additional);
+        }
+    }
+}
+
+/// A [`FormData`] that builds the document in memory.
+pub type FormDataBuf = FormData<Vec<u8>>;
+
+/// A [`FormData`] that writes to a boxed writer, so that builders for different writers have the
+/// same type. Created with [`FormDataBoxed::boxed`] or [`FormData::into_boxed`].
+pub type FormDataBoxed = FormData<Box<dyn Write + Send>>;
+
+impl FormDataBoxed {
+    /// Starts writing a document to `writer`, boxed.
+    ///
+    /// ```
+    /// # use form_data_builder::FormDataBoxed;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let socket = Vec::new();
+    /// let mut form = FormDataBoxed::boxed(socket);
+    /// form.write_field("cute", "yes")?;
+    /// let socket: Box<dyn std::io::Write + Send> = form.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`].
+    pub fn boxed<W2: Write + Send + 'static>(writer: W2) -> FormDataBoxed {
+        FormData::new(Box::new(writer))
+    }
+
+    /// Like [`FormDataBoxed::boxed`], but with a fixed boundary, like
+    /// [`FormData::with_boundary`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormData::with_boundary`].
+    pub fn boxed_with_boundary<W2: Write + Send + 'static>(
+        writer: W2,
+        boundary: &str,
+    ) -> Result<FormDataBoxed> {
+        FormData::with_boundary(Box::new(writer), boundary)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl FormData<bytes::buf::Writer<bytes::BytesMut>> {
+    /// Finishes the document like [`FormData::finish`], freezing the [`bytes::BytesMut`] it was
+    /// written to into a [`bytes::Bytes`].
+    ///
+    /// ```
+    /// # use form_data_builder::FormData;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use bytes::BufMut;
+    ///
+    /// let mut form = FormData::new(bytes::BytesMut::new().writer());
+    /// form.write_field("cute", "yes")?;
+    /// let body: bytes::Bytes = form.finish_into_bytes()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormData::finish`].
+    pub fn finish_into_bytes(&mut self) -> Result<bytes::Bytes> {
+        self.finish().map(|writer| writer.into_inner().freeze())
+    }
+}
+
+impl FormDataBuf {
+    /// Starts writing a document to a `Vec<u8>` with room for `capacity` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`FormData::new`].
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> FormDataBuf {
+        FormData::new(Vec::with_capacity(capacity))
+    }
+
+    /// Finishes the document like [`FormData::finish`], returning it as a [`bytes::Bytes`] (without
+    /// copying it) to hand to an HTTP client.
+    ///
+    /// ```
+    /// # use form_data_builder::FormDataBuf;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut form = FormDataBuf::with_capacity(0);
+    /// form.write_field("cute", "yes")?;
+    /// let body: bytes::Bytes = form.finish_into_bytes()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormData::finish`].
+    #[cfg(feature = "bytes")]
+    pub fn finish_into_bytes(&mut self) -> Result<bytes::Bytes> {
+        self.finish().map(bytes::Bytes::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FormData, FormDataBoxed, FormDataError};
+    use std::ffi::OsString;
+    use std::io::{Cursor, ErrorKind};
+    use std::path::Path;
+
+    /// A reader that always fails, like a file on a disk that went away.
+    pub(crate) struct Broken;
+
+    impl std::io::Read for Broken {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                ErrorKind::ConnectionReset,
+                "the disk fell off",
+            ))
+        }
+    }
+
+    /// This test uses a `multipart/form-data` document generated by Firefox as a test case.
+    #[test]
+    fn smoke_test() {
+        const CORRECT: &[u8] = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/form-data.bin"
+        ));
+        const CORRO: &str =
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/corro.svg"));
+        const TEXT_A: &str =
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/text-a.txt"));
+        const TEXT_B: &str =
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/text-b.txt"));
+
+        let mut form = FormData::new(Vec::new());
+        assert_eq!(form.boundary.len(), 68);
+        assert_eq!(form.boundary[..36], "-".repeat(36));
         // cheat and use the boundary Firefox generated
-        form.boundary = "---------------------------20598614689265574691413388431".to_owned();
+        form.boundary =
+            super::Boundary::Static("---------------------------20598614689265574691413388431");
 
         form.write_path(
             "file-a",
@@ -275,5 +3678,1108 @@ mod tests {
         form.write_field("text-b", TEXT_B.trim()).unwrap();
 
         assert_eq!(form.finish().unwrap(), CORRECT);
+        assert_eq!(form.written, CORRECT.len() as u64);
+    }
+
+    #[test]
+    fn debug_redacts() {
+        let mut form = FormData::new(Vec::new());
+        form.write_field("cute", "yes").unwrap();
+        form.write_field_secret("token", "hunter2").unwrap();
+        let debug = format!("{form:?}");
+        assert!(debug.contains("\"yes\""));
+        assert!(debug.contains("[REDACTED]"));
+        assert!(!debug.contains("hunter2"));
+        form.finish().unwrap();
+
+        let debug = format!("{:?}", crate::Part::secret("token", "hunter2"));
+        assert!(!debug.contains("hunter2"));
+    }
+
+    #[test]
+    fn time_limit() {
+        struct Slow;
+
+        impl std::io::Read for Slow {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                buf[0] = b'!';
+                Ok(1)
+            }
+        }
+
+        let mut form = FormData::new(Vec::new());
+        form.set_time_limit(std::time::Duration::from_millis(20));
+        let err = form
+            .write_file("slow", Slow, None, "text/plain")
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(form.abort().is_some());
+        assert!(form.abort().is_none());
+    }
+
+    #[test]
+    fn rate_limit() {
+        let mut form = FormData::new(Vec::new());
+        form.set_rate_limit(std::num::NonZeroU64::new(1000).unwrap());
+        let started = std::time::Instant::now();
+        form.write_part(&crate::Part::text("slow", "!".repeat(100)))
+            .unwrap();
+        assert!(started.elapsed() >= std::time::Duration::from_millis(100));
+        form.finish().unwrap();
+    }
+
+    #[test]
+    fn template_open_error() {
+        let template: crate::FormDataTemplate = [crate::Part::path(
+            "missing",
+            "testdata/missing.bin",
+            "application/octet-stream",
+        )]
+        .into_iter()
+        .collect();
+        let err = template.emit(FormData::new(Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(err.to_string().starts_with("part \"missing\": "));
+    }
+
+    #[test]
+    fn last_progress() {
+        use std::io::Read;
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        assert_eq!(form.last_progress(), crate::Progress::default());
+        form.write_field("cute", "yes").unwrap();
+        let complete = form.get_ref().unwrap().len() as u64;
+        form.write_file("corro", b"<svg".chain(Broken), None, "image/svg+xml")
+            .unwrap_err();
+        let progress = form.last_progress();
+        assert_eq!((progress.parts, progress.bytes), (1, complete));
+        assert!(form.get_ref().unwrap().len() as u64 > complete);
+        form.abort();
+    }
+
+    #[test]
+    fn write_file_buffered() {
+        use std::io::{BufReader, Read};
+
+        const CORRO: &[u8] = include_bytes!("../testdata/corro.svg");
+        let mut buffered = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let reader = BufReader::with_capacity(100, CORRO);
+        buffered
+            .write_file_buffered("corro", reader, None, "image/svg+xml")
+            .unwrap();
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected
+            .write_file("corro", CORRO, None, "image/svg+xml")
+            .unwrap();
+        assert_eq!(buffered.finish().unwrap(), expected.finish().unwrap());
+
+        let mut form = FormData::new(Vec::new());
+        let reader = BufReader::with_capacity(3, b"<svg".chain(Broken));
+        let err = form
+            .write_file_buffered("corro", reader, None, "image/svg+xml")
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert!(matches!(
+            err.get_ref().and_then(|err| err.downcast_ref()),
+            Some(crate::FormDataError::PartBody { offset: 4, .. })
+        ));
+        form.abort();
+    }
+
+    #[test]
+    fn write_file_concat() {
+        use std::io::Read;
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let sources: [&[u8]; 4] = [b"<svg", b"", b"/", b">"];
+        form.write_file_concat("corro", None, "image/svg+xml", sources)
+            .unwrap();
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected
+            .write_file("corro", &b"<svg/>"[..], None, "image/svg+xml")
+            .unwrap();
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+
+        let mut form = FormData::new(Vec::new());
+        let sources: [Box<dyn Read>; 3] = [
+            Box::new(&b"<svg"[..]),
+            Box::new(&b"/"[..]),
+            Box::new(Broken),
+        ];
+        let err = form
+            .write_file_concat("corro", None, "image/svg+xml", sources)
+            .unwrap_err();
+        assert!(matches!(
+            err.get_ref().and_then(|err| err.downcast_ref()),
+            Some(crate::FormDataError::PartBody { offset: 5, .. })
+        ));
+        form.abort();
+    }
+
+    #[cfg(feature = "dedup")]
+    #[test]
+    fn duplicate_content_id() {
+        use crate::{DuplicatePolicy, Part};
+        use sha2::{Digest, Sha256};
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.set_duplicate_policy(DuplicatePolicy::ContentId);
+        form.write_part(&Part::file("a", &b"<svg/>"[..], "image/svg+xml"))
+            .unwrap();
+        form.write_part(&Part::text("cute", "yes")).unwrap();
+        form.write_part(&Part::file("b", &b"<svg/>"[..], "image/svg+xml"))
+            .unwrap();
+        form.write_part(&Part::file("c", &b"<png/>"[..], "image/png"))
+            .unwrap();
+        let document = String::from_utf8(form.finish().unwrap()).unwrap();
+        let id = super::dedup::content_id(&Sha256::digest(b"<svg/>").into());
+        assert_eq!(document.matches(&id).count(), 2);
+        assert_eq!(document.matches("Content-ID: ").count(), 3);
+        assert_eq!(document.matches("<svg/>").count(), 1);
+        assert!(document.contains(&format!("Content-ID: {id}\r\n\r\n\r\n--boundary")));
+    }
+
+    #[cfg(feature = "content-md5")]
+    #[test]
+    fn content_md5() {
+        use crate::Part;
+        use md5::Digest;
+
+        let parts = [
+            Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+            Part::file("cute", &b"yes"[..], "text/plain"),
+            Part::text("field", "no header"),
+        ];
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.set_content_md5(true);
+        let predicted = form.predicted_len(&parts);
+        for part in &parts {
+            form.write_part(part).unwrap();
+        }
+        let document = form.finish().unwrap();
+        assert_eq!(predicted, document.len() as u64);
+
+        let parsed = crate::parse::FormDataParts::parse(&document, "boundary").unwrap();
+        for part in &parsed {
+            let md5 = part
+                .headers()
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("Content-MD5"))
+                .map(|(_, value)| value.clone());
+            let expected = part
+                .content_type()
+                .map(|_| super::content_md5::content_md5(&md5::Md5::digest(part.body())));
+            assert_eq!(md5, expected);
+        }
+    }
+
+    #[test]
+    fn resume() {
+        use std::io::{Cursor, Read};
+
+        let template: crate::FormDataTemplate = [
+            crate::Part::text("cute", "yes"),
+            crate::Part::file("corro", &b"<svg/>"[..], "image/svg+xml"),
+        ]
+        .into_iter()
+        .collect();
+        let expected = template
+            .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+            .unwrap();
+
+        let mut form = FormData::with_boundary(Cursor::new(Vec::new()), "boundary").unwrap();
+        form.write_part(&template.parts()[0]).unwrap();
+        form.write_file("corro", b"<svg".chain(Broken), None, "image/svg+xml")
+            .unwrap_err();
+        form.resume().unwrap();
+        form.resume().unwrap();
+        form.write_part(&template.parts()[1]).unwrap();
+        assert_eq!(form.finish().unwrap().into_inner(), expected);
+        assert!(form.resume().is_err());
+
+        let mut form = FormData::with_boundary(Cursor::new(Vec::new()), "boundary").unwrap();
+        form.write_part(&template.parts()[0]).unwrap();
+        form.write_file("corro", b"<svg".chain(Broken), None, "image/svg+xml")
+            .unwrap_err();
+        let document = template.resume(&mut form).unwrap();
+        assert_eq!(document.into_inner(), expected);
+
+        // parts left out by the file type policy count towards where to carry on
+        #[cfg(unix)]
+        {
+            let template: crate::FormDataTemplate = [
+                crate::Part::text("cute", "yes"),
+                crate::Part::path("null", "/dev/null", "text/plain"),
+                crate::Part::file("corro", &b"<svg/>"[..], "image/svg+xml"),
+            ]
+            .into_iter()
+            .collect();
+            let mut form = FormData::with_boundary(Cursor::new(Vec::new()), "boundary").unwrap();
+            form.set_file_type_policy(crate::FileTypePolicy::Skip);
+            form.write_part(&template.parts()[0]).unwrap();
+            form.write_part(&template.parts()[1]).unwrap();
+            assert_eq!(form.last_progress().parts, 2);
+            form.write_file("corro", b"<svg".chain(Broken), None, "image/svg+xml")
+                .unwrap_err();
+            let document = template.resume(&mut form).unwrap();
+            assert_eq!(document.into_inner(), expected);
+        }
+    }
+
+    #[test]
+    fn finish_staged() {
+        let mut writer = std::io::Cursor::new(b"junk".to_vec());
+        writer.set_position(4);
+        let mut form = FormData::with_boundary(writer, "boundary").unwrap();
+        form.write_field("cute", "yes").unwrap();
+        let (writer, len) = form.finish_staged().unwrap();
+        assert_eq!(writer.position(), 4);
+        assert_eq!(writer.get_ref().len() as u64, len + 4);
+        assert_eq!(form.headers()[1], ("Content-Length", len.to_string()));
+
+        let mut form =
+            FormData::with_boundary(std::io::Cursor::new(Vec::new()), "boundary").unwrap();
+        form.write_field("cute", "yes").unwrap();
+        form.writer.as_mut().unwrap().set_position(0);
+        assert!(form.finish_staged().is_err());
+    }
+
+    #[test]
+    fn manifest() {
+        let mut form = FormData::new(Vec::new());
+        form.write_field("cute", "yes").unwrap();
+        form.write_raw_part(
+            b"Content-Disposition: form-data; name=\"raw\"\r\n\r\n",
+            &b"raw body"[..],
+        )
+        .unwrap();
+        let mut part = form.start_text_part("log").unwrap();
+        std::io::Write::write_all(&mut part, b"line\n").unwrap();
+        part.end().unwrap();
+        form.write_path("corro", "testdata/corro.svg", "image/svg+xml")
+            .unwrap();
+        let document = form.finish().unwrap();
+
+        let manifest = form.manifest();
+        assert_eq!(
+            manifest
+                .iter()
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>(),
+            ["cute", "raw", "log", "corro"]
+        );
+        assert_eq!(manifest[3].content_type.as_deref(), Some("image/svg+xml"));
+        let parts = crate::parse::FormDataParts::parse(&document, form.boundary()).unwrap();
+        for entry in &manifest {
+            let start = usize::try_from(entry.offset).unwrap();
+            let end = start + usize::try_from(entry.size).unwrap();
+            assert_eq!(
+                &document[start..end],
+                parts.get(&entry.name).unwrap().body()
+            );
+        }
+    }
+
+    #[cfg(feature = "manifest")]
+    #[test]
+    fn manifest_part() {
+        use crate::manifest;
+        use sha2::{Digest, Sha256};
+
+        let mut form = FormData::new(Vec::new());
+        form.set_manifest_part(Some("__manifest"));
+        form.write_field("cute", "yes").unwrap();
+        form.write_raw_part(
+            b"Content-Disposition: form-data; name=\"raw\"\r\n\r\n",
+            &b"raw body"[..],
+        )
+        .unwrap();
+        let mut part = form.start_text_part("log").unwrap();
+        std::io::Write::write_all(&mut part, b"line\n").unwrap();
+        part.end().unwrap();
+        form.write_path("corro", "testdata/corro.svg", "image/svg+xml")
+            .unwrap();
+        let document = form.finish().unwrap();
+
+        let manifest = form.manifest();
+        assert_eq!(
+            manifest
+                .iter()
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>(),
+            ["cute", "raw", "log", "corro", "__manifest"]
+        );
+        let parts = crate::parse::FormDataParts::parse(&document, form.boundary()).unwrap();
+        for entry in &manifest {
+            let mut sha256 = Sha256::new();
+            sha256.update(parts.get(&entry.name).unwrap().body());
+            assert_eq!(entry.sha256, Some(manifest::hex(sha256)), "{}", entry.name);
+        }
+
+        // bodies aren't hashed without a manifest part
+        let mut form = FormData::new(Vec::new());
+        form.write_field("cute", "yes").unwrap();
+        form.finish().unwrap();
+        assert_eq!(form.manifest()[0].sha256, None);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        use std::io::Read;
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary")
+            .unwrap()
+            .gzip()
+            .unwrap();
+        form.write_field("cute", "yes").unwrap();
+        let compressed = form.finish_gzip().unwrap();
+        assert_eq!(form.headers().len(), 2);
+        let mut document = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..])
+            .read_to_end(&mut document)
+            .unwrap();
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        assert_eq!(document, expected.finish().unwrap());
+
+        let mut form = FormData::new(Vec::new());
+        form.write_field("cute", "yes").unwrap();
+        let mut form = form.gzip().unwrap_err().into_inner();
+        // the builder comes back as it was
+        form.write_field("cute", "still").unwrap();
+        form.finish().unwrap();
+    }
+
+    #[test]
+    fn single_write_header() {
+        #[derive(Default)]
+        struct Recorder(Vec<Vec<u8>>);
+
+        impl std::io::Write for Recorder {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.to_vec());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut form = FormData::with_boundary(Recorder::default(), "boundary").unwrap();
+        form.write_file(
+            "corro",
+            &b"<svg/>"[..],
+            Some("corro.svg".as_ref()),
+            "image/svg+xml",
+        )
+        .unwrap();
+        let writes = form.finish().unwrap().0;
+        assert_eq!(
+            writes.first().unwrap(),
+            b"--boundary\r\n\
+              Content-Disposition: form-data; name=\"corro\"; filename=\"corro.svg\"\r\n\
+              Content-Type: image/svg+xml\r\n\
+              \r\n"
+        );
+        assert_eq!(writes.last().unwrap(), b"--boundary--\r\n");
+    }
+
+    #[test]
+    fn read_ahead() {
+        let path = "testdata/rustacean-flat-noshadow.png";
+        let [a, b] = [true, false].map(|read_ahead| {
+            let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+            form.set_read_ahead(read_ahead);
+            form.write_path("ferris", path, "image/png").unwrap();
+            form.write_part(&crate::Part::path("ferris", path, "image/png"))
+                .unwrap();
+            form.finish().unwrap()
+        });
+        assert!(a.len() as u64 > 2 * std::fs::metadata(path).unwrap().len());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn empty_parts() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_empty_field("nickname").unwrap();
+        form.write_empty_file("avatar", Some("".as_ref()), "application/octet-stream")
+            .unwrap();
+        assert_eq!(
+            form.finish().unwrap(),
+            b"--boundary\r\n\
+              Content-Disposition: form-data; name=\"nickname\"\r\n\
+              \r\n\
+              \r\n\
+              --boundary\r\n\
+              Content-Disposition: form-data; name=\"avatar\"; filename=\"\"\r\n\
+              Content-Type: application/octet-stream\r\n\
+              \r\n\
+              \r\n\
+              --boundary--\r\n"
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        let mut form = FormData::new(Vec::new());
+        form.write_bool("a", false).unwrap();
+        form.write_int("b", u64::MAX).unwrap();
+        form.write_int("c", i64::MIN).unwrap();
+        form.write_float("d", 1e21).unwrap();
+        form.write_float("e", 1.5e-7).unwrap();
+        form.write_float("f", 3.0).unwrap();
+        for value in [f64::NAN, f64::INFINITY] {
+            let err = form.write_float("g", value).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+        form.write_int("h", u128::MAX).unwrap();
+        form.write_int("i", usize::MIN).unwrap();
+        form.write_int("j", isize::from(-1_i8)).unwrap();
+        assert_eq!(
+            form.describe(),
+            "a: field \"false\", 5 bytes\n\
+             b: field \"18446744073709551615\", 20 bytes\n\
+             c: field \"-9223372036854775808\", 20 bytes\n\
+             d: field \"1000000000000000000000\", 22 bytes\n\
+             e: field \"0.00000015\", 10 bytes\n\
+             f: field \"3\", 1 bytes\n\
+             h: field \"340282366920938463463374607431768211455\", 39 bytes\n\
+             i: field \"0\", 1 bytes\n\
+             j: field \"-1\", 2 bytes\n"
+        );
+        form.finish().unwrap();
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn datetime() {
+        let t = time::OffsetDateTime::from_unix_timestamp(855_764_991).unwrap();
+        let mut form = FormData::new(Vec::new());
+        form.write_datetime("a", t).unwrap();
+        form.write_datetime(
+            "b",
+            t.to_offset(time::UtcOffset::from_hms(-8, 0, 0).unwrap()),
+        )
+        .unwrap();
+        let err = form
+            .write_datetime("c", t.replace_year(-1).unwrap())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(
+            form.describe(),
+            "a: field \"1997-02-12T16:29:51Z\", 20 bytes\n\
+             b: field \"1997-02-12T08:29:51-08:00\", 25 bytes\n"
+        );
+        form.finish().unwrap();
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid() {
+        let mut form = FormData::new(Vec::new());
+        form.write_uuid(
+            "id",
+            uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8),
+        )
+        .unwrap();
+        assert_eq!(
+            form.describe(),
+            "id: field \"67e55044-10b1-426f-9247-bb680e5fe0c8\", 36 bytes\n"
+        );
+        form.finish().unwrap();
+    }
+
+    #[test]
+    fn normalize_newlines() {
+        for (value, expected) in [
+            ("", ""),
+            ("yes", "yes"),
+            ("a\r\nb", "a\r\nb"),
+            ("a\nb\rc", "a\r\nb\r\nc"),
+            ("\n\r\r\n\n\r", "\r\n\r\n\r\n\r\n\r\n"),
+        ] {
+            assert_eq!(
+                super::normalize_newlines(value.as_bytes()),
+                expected.as_bytes()
+            );
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn write_csv() {
+        #[derive(serde::Serialize)]
+        struct Crab {
+            name: &'static str,
+            legs: u8,
+        }
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let crabs = [
+            Crab {
+                name: "ferris",
+                legs: 10,
+            },
+            Crab {
+                name: "corro, the unsafe",
+                legs: 8,
+            },
+        ];
+        form.write_csv("crabs", None, &crabs).unwrap();
+        let document = form.finish().unwrap();
+        let parts = crate::parse::FormDataParts::parse(&document, "boundary").unwrap();
+        let part = parts.get("crabs").unwrap();
+        assert_eq!(part.content_type(), Some("text/csv"));
+        assert_eq!(
+            part.body(),
+            b"name,legs\nferris,10\n\"corro, the unsafe\",8\n"
+        );
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_field("cute", "yes").unwrap();
+        let progress = form.last_progress();
+        let err = form
+            .write_csv("crabs", None, [Legs(10), Legs(0)])
+            .unwrap_err();
+        assert!(err.to_string().contains("no legs"), "{err}");
+        assert_eq!(form.last_progress(), progress);
+        form.abort();
+    }
+
+    #[cfg(feature = "ndjson")]
+    #[test]
+    fn write_ndjson() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let items = [("ferris", 10), ("corro\n", 8)];
+        form.write_ndjson("crabs", Some("crabs.ndjson".as_ref()), items)
+            .unwrap();
+        let document = form.finish().unwrap();
+        let parts = crate::parse::FormDataParts::parse(&document, "boundary").unwrap();
+        let part = parts.get("crabs").unwrap();
+        assert_eq!(part.content_type(), Some("application/x-ndjson"));
+        assert_eq!(part.body(), b"[\"ferris\",10]\n[\"corro\\n\",8]\n");
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_field("cute", "yes").unwrap();
+        let progress = form.last_progress();
+        let err = form
+            .write_ndjson("crabs", None, [Legs(10), Legs(0)])
+            .unwrap_err();
+        assert!(err.to_string().contains("no legs"), "{err}");
+        assert_eq!(form.last_progress(), progress);
+        form.abort();
+    }
+
+    /// Fails to serialize when there are no legs.
+    #[cfg(any(feature = "csv", feature = "ndjson"))]
+    struct Legs(u8);
+
+    #[cfg(any(feature = "csv", feature = "ndjson"))]
+    impl serde::Serialize for Legs {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if self.0 == 0 {
+                return Err(serde::ser::Error::custom("no legs"));
+            }
+            serializer.serialize_u8(self.0)
+        }
+    }
+
+    #[cfg(feature = "quick-xml")]
+    #[test]
+    fn write_xml() {
+        #[derive(serde::Serialize)]
+        struct Order {
+            #[serde(rename = "@id")]
+            id: u32,
+            item: Vec<&'static str>,
+        }
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let order = Order {
+            id: 7,
+            item: vec!["crab", "<ferris>"],
+        };
+        form.write_xml("order", &order).unwrap();
+        let err = form.write_xml("number", &7).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        let document = form.finish().unwrap();
+        let parts = crate::parse::FormDataParts::parse(&document, "boundary").unwrap();
+        let part = parts.get("order").unwrap();
+        assert_eq!(part.content_type(), Some("application/xml"));
+        assert_eq!(
+            part.body(),
+            b"<Order id=\"7\"><item>crab</item><item>&lt;ferris&gt;</item></Order>"
+        );
+        assert!(parts.get("number").is_none());
+    }
+
+    #[cfg(feature = "prost")]
+    #[test]
+    fn write_protobuf() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_protobuf("cute", &String::from("yes")).unwrap();
+        let document = form.finish().unwrap();
+        let parts = crate::parse::FormDataParts::parse(&document, "boundary").unwrap();
+        let part = parts.get("cute").unwrap();
+        assert_eq!(part.content_type(), Some("application/x-protobuf"));
+        assert_eq!(part.body(), b"\x0a\x03yes");
+    }
+
+    #[cfg(feature = "nested")]
+    #[test]
+    fn write_nested() {
+        use crate::NestedNaming;
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let value =
+            serde_json::json!({ "name": "ferris", "tags": ["crab", "unsafe"], "age": null });
+        form.write_nested("crab", &value, NestedNaming::Dots)
+            .unwrap();
+        form.write_nested("cute", &true, NestedNaming::Brackets)
+            .unwrap();
+        let err = form
+            .write_nested(
+                "bad",
+                &std::collections::HashMap::from([((1, 2), 3)]),
+                NestedNaming::Brackets,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        for (name, value) in [
+            ("crab.name", "ferris"),
+            ("crab.tags.0", "crab"),
+            ("crab.tags.1", "unsafe"),
+            ("cute", "true"),
+        ] {
+            expected.write_field(name, value).unwrap();
+        }
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+    }
+
+    #[test]
+    fn write_files() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let err = form
+            .write_files(
+                "files",
+                ["testdata/corro.svg", "testdata/missing.png"],
+                |path| format!("image/{}", path.extension().unwrap().to_str().unwrap()),
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        match err.get_ref().and_then(|err| err.downcast_ref()) {
+            Some(FormDataError::File { index, path, .. }) => {
+                assert_eq!(
+                    (*index, path.as_path()),
+                    (1, Path::new("testdata/missing.png"))
+                );
+            }
+            _ => panic!("unexpected error {err:?}"),
+        }
+        assert_eq!(
+            form.describe(),
+            "files: file \"corro.svg\" (image/svg), 7576 bytes\n"
+        );
+        form.finish().unwrap();
+    }
+
+    #[test]
+    fn canonical() {
+        let parts = [
+            crate::Part::file("doc", &b"{}"[..], "Application/JSON ;Version=2")
+                .with_filename("doc.json"),
+            crate::Part::text("note", "a\nb"),
+        ];
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.set_canonical(true);
+        form.set_header_order(&[crate::PartHeader::ContentType]);
+        let predicted = form.predicted_len(&parts);
+        for part in &parts {
+            form.write_part(part).unwrap();
+        }
+        let document = form.finish().unwrap();
+        assert_eq!(predicted, document.len() as u64);
+        assert_eq!(
+            String::from_utf8(document).unwrap(),
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"doc\"; filename=\"doc.json\"\r\n\
+             Content-Type: application/json; version=2\r\n\
+             \r\n\
+             {}\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"note\"\r\n\
+             \r\n\
+             a\r\nb\r\n\
+             --boundary--\r\n"
+        );
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.set_canonical(true);
+        let err = form.write_field_reader("note", &b"a\nb"[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(form.start_text_part("note").is_err());
+        form.start_part("log", Some("log.txt".as_ref()), None)
+            .unwrap()
+            .end()
+            .unwrap();
+        assert_eq!(form.manifest().len(), 1);
+        form.finish().unwrap();
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn sign_hmac() {
+        use std::io::Write;
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary")
+            .unwrap()
+            .sign_hmac(b"key", "signature")
+            .unwrap();
+        form.write_field("cute", "yes").unwrap();
+        form.write_path("corro", "testdata/corro.svg", "image/svg+xml")
+            .unwrap();
+        let document = form.finish_signed().unwrap();
+        assert!(form.finish_signed().is_err());
+
+        let parts = crate::parse::FormDataParts::parse(&document, "boundary").unwrap();
+        let signature = std::str::from_utf8(parts.get("signature").unwrap().body()).unwrap();
+        let start = document
+            .windows(12)
+            .rposition(|window| window == b"--boundary\r\n")
+            .unwrap();
+        assert!(String::from_utf8_lossy(&document[start..]).contains("name=\"signature\""));
+        let mut signer = crate::HmacWriter::new(b"key", "signature");
+        signer.write_all(&document[..start]).unwrap();
+        assert_eq!(signature, signer.hex());
+
+        let mut form = FormData::new(Vec::new());
+        form.write_field("cute", "yes").unwrap();
+        let mut form = form
+            .sign_hmac(b"key", "signature")
+            .unwrap_err()
+            .into_inner();
+        form.write_field("cute", "still").unwrap();
+        form.finish().unwrap();
+    }
+
+    #[test]
+    fn boxed() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+        assert_send::<FormDataBoxed>();
+        assert_send::<FormData<Vec<u8>>>();
+        assert_sync::<FormData<Vec<u8>>>();
+
+        let mut form = FormDataBoxed::boxed_with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_field("cute", "yes").unwrap();
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        let mut form = std::thread::spawn(move || {
+            form.write_field("crab", "ferris").unwrap();
+            form
+        })
+        .join()
+        .unwrap();
+        expected.write_field("crab", "ferris").unwrap();
+        assert_eq!(form.describe(), expected.describe());
+        form.finish().unwrap();
+        expected.finish().unwrap();
+
+        let mut form = FormData::new(Vec::new());
+        form.finish().unwrap();
+        assert!(form.into_boxed().is_err());
+    }
+
+    #[test]
+    fn strict_names() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.set_strict_names(true);
+        for (name, reason) in [
+            ("", "it is empty"),
+            (" \t", "it is only whitespace"),
+            ("say \"cute\"", "it contains a quotation mark"),
+            (
+                "cute\r\nContent-Type: evil",
+                "it contains a control character",
+            ),
+            ("cute\0", "it contains a control character"),
+        ] {
+            let err = form.write_field(name, "yes").unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            match err.get_ref().and_then(|err| err.downcast_ref()) {
+                Some(FormDataError::InvalidName {
+                    name: rejected,
+                    reason: why,
+                }) => {
+                    assert_eq!((rejected.as_str(), *why), (name, reason));
+                }
+                _ => panic!("unexpected error {err:?}"),
+            }
+        }
+        form.write_field("cute name", "yes").unwrap();
+        assert_eq!(form.describe(), "cute name: field \"yes\", 3 bytes\n");
+
+        form.set_strict_names(false);
+        form.write_field(" ", "yes").unwrap();
+        form.finish().unwrap();
+    }
+
+    #[test]
+    fn write_field_owned() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_field(String::from("cute"), String::from("yes"))
+            .unwrap();
+        form.write_field_opt("nickname", Some(String::from("ferris")))
+            .unwrap();
+        form.write_field_opt("comment", None::<String>).unwrap();
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        expected.write_field("nickname", "ferris").unwrap();
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+    }
+
+    #[test]
+    fn write_field_reader() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_field_reader("cute", Cursor::new("yes")).unwrap();
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn finish_into_bytes() {
+        use bytes::BufMut;
+
+        let mut vec = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let mut bytes_mut =
+            FormData::with_boundary(bytes::BytesMut::new().writer(), "boundary").unwrap();
+        vec.write_field("cute", "yes").unwrap();
+        bytes_mut.write_field("cute", "yes").unwrap();
+        let vec = vec.finish_into_bytes().unwrap();
+        assert_eq!(bytes_mut.finish_into_bytes().unwrap(), vec);
+    }
+
+    #[test]
+    fn predicted_len() {
+        let parts = [
+            crate::Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+            crate::Part::file("ferris", &b"\x89PNG"[..], "image/png"),
+            crate::Part::text("cute", "yes"),
+            crate::Part::text("haiku", "crab walks sideways\nclaws click\r"),
+            crate::Part::file("ferris", &b"\x89PNG"[..], "image/png").with_filename("fërris.png"),
+        ];
+        for enabled in [false, true] {
+            let mut form = crate::FormDataBuf::with_capacity(0);
+            form.set_file_metadata(enabled);
+            if enabled {
+                form.set_filename_encoding(crate::FilenameEncoding::PercentEncode);
+            }
+            form.set_normalize_newlines(enabled);
+            #[cfg(feature = "manifest")]
+            form.set_manifest_part(enabled.then_some("__manifest"));
+            let predicted = form.predicted_len(&parts);
+            form.reserve(&parts);
+            for part in &parts {
+                form.write_part(part).unwrap();
+            }
+            assert_eq!(form.finish().unwrap().len() as u64, predicted);
+        }
+
+        let form = crate::FormDataBuf::with_capacity(0);
+        let missing = crate::Part::path("corro", "testdata/missing.svg", "image/svg+xml");
+        let empty =
+            crate::Part::file("corro", &b""[..], "image/svg+xml").with_filename("missing.svg");
+        assert_eq!(form.predicted_len(&[missing]), form.predicted_len(&[empty]));
+    }
+
+    #[cfg(feature = "dedup")]
+    #[test]
+    fn predicted_len_dedup() {
+        use crate::{DuplicatePolicy, Part};
+
+        let parts = [
+            Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+            Part::file(
+                "again",
+                &include_bytes!("../testdata/corro.svg")[..],
+                "image/svg+xml",
+            ),
+            Part::text("cute", "yes"),
+            Part::file("cute", &b"yes"[..], "text/plain"),
+        ];
+        for policy in [
+            DuplicatePolicy::Keep,
+            DuplicatePolicy::Skip,
+            DuplicatePolicy::ContentId,
+        ] {
+            let mut form = crate::FormDataBuf::with_capacity(0);
+            form.set_duplicate_policy(policy);
+            form.write_part(&parts[0]).unwrap();
+            let predicted = form.written + form.predicted_len(&parts[1..]);
+            for part in &parts[1..] {
+                form.write_part(part).unwrap();
+            }
+            assert_eq!(form.finish().unwrap().len() as u64, predicted, "{policy:?}");
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn reserve_bytes_mut() {
+        use bytes::BufMut;
+
+        let parts = [crate::Part::path(
+            "corro",
+            "testdata/corro.svg",
+            "image/svg+xml",
+        )];
+        let mut form = FormData::new(bytes::BytesMut::new().writer());
+        form.reserve(&parts);
+        let capacity = form.get_ref().unwrap().get_ref().capacity();
+        form.write_part(&parts[0]).unwrap();
+        let document = form.finish().unwrap().into_inner();
+        assert!(capacity >= document.len());
+        assert_eq!(document.capacity(), capacity);
+    }
+
+    #[test]
+    fn flush_per_part() {
+        let mut form = FormData::new(std::io::BufWriter::new(Vec::new()));
+        form.write_field("cute", "yes").unwrap();
+        assert!(form.writer.as_ref().unwrap().get_ref().is_empty());
+        form.set_flush_per_part(true);
+        form.write_field("cute", "yes").unwrap();
+        assert_eq!(
+            form.writer.as_ref().unwrap().get_ref().len() as u64,
+            form.written
+        );
+        form.finish().unwrap();
+    }
+
+    #[test]
+    fn fork() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_field("shared", "yes").unwrap();
+        let mut forked = form.fork(form.get_ref().unwrap().clone()).unwrap();
+        form.write_field("cute", "yes").unwrap();
+        forked.write_field("cute", "very").unwrap();
+        assert_eq!(
+            forked.describe(),
+            "shared: field \"yes\", 3 bytes\ncute: field \"very\", 4 bytes\n"
+        );
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("shared", "yes").unwrap();
+        expected.write_field("cute", "very").unwrap();
+        assert_eq!(forked.finish().unwrap(), expected.finish().unwrap());
+        assert_eq!(forked.written, expected.written);
+        form.finish().unwrap();
+        assert!(form.fork(Vec::new()).is_err());
+    }
+
+    #[cfg(all(feature = "leak-check", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "without `finish()` or `abort()`")]
+    fn leak_check() {
+        let mut form = FormData::new(Vec::new());
+        form.write_field("cute", "yes").unwrap();
+    }
+
+    #[test]
+    fn quoted_boundary() {
+        let form = FormData::with_boundary(Vec::new(), "odd boundary?").unwrap();
+        assert_eq!(
+            form.content_type_header(),
+            "multipart/form-data; boundary=\"odd boundary?\""
+        );
+        assert_eq!(
+            crate::parse::boundary_from_content_type(&form.content_type_header()).as_deref(),
+            Some("odd boundary?")
+        );
+        #[cfg(feature = "mime")]
+        assert_eq!(
+            form.content_type().get_param(mime::BOUNDARY).unwrap(),
+            "odd boundary?"
+        );
+    }
+
+    #[test]
+    fn start_part() {
+        use std::io::Write;
+
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let mut part = form.start_part("cute", None, None).unwrap();
+        part.write_all(b"y").unwrap();
+        part.write_all(b"es").unwrap();
+        part.end().unwrap();
+        form.start_part("corro", Some("corro.svg".as_ref()), Some("image/svg+xml"))
+            .unwrap()
+            .end()
+            .unwrap();
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        expected
+            .write_file(
+                "corro",
+                &b""[..],
+                Some("corro.svg".as_ref()),
+                "image/svg+xml",
+            )
+            .unwrap();
+        assert_eq!(form.written, expected.written);
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+        assert_eq!(
+            form.describe().lines().nth(1),
+            expected.describe().lines().nth(1)
+        );
+    }
+
+    #[test]
+    fn write_raw_part() {
+        let headers = b"Content-Type: text/plain\r\n\
+                        Content-Disposition: form-data; name=\"cute\"\r\n\
+                        \r\n";
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_raw_part(headers, &b"yes"[..]).unwrap();
+        assert_eq!(form.describe(), "cute: file (text/plain), 3 bytes\n");
+        assert!(form.write_raw_part(b"X: y\r\n", &b""[..]).is_err());
+        let document = form.finish().unwrap();
+        assert_eq!(form.written, document.len() as u64);
+
+        let parts = crate::parse::FormDataParts::parse(&document, "boundary").unwrap();
+        assert_eq!(parts.get("cute").unwrap().body(), b"yes");
+        assert_eq!(
+            parts.get("cute").unwrap().content_type(),
+            Some("text/plain")
+        );
+    }
+
+    #[test]
+    fn disposition_params() {
+        let part = crate::Part::file("corro", &b"<svg/>"[..], "image/svg+xml")
+            .with_filename("corro.svg")
+            .with_modification_date(
+                std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(855_764_991),
+            )
+            .with_size(6);
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let predicted = form.predicted_len(std::slice::from_ref(&part));
+        form.write_part(&part).unwrap();
+        let document = form.finish().unwrap();
+        assert_eq!(document.len() as u64, predicted);
+        assert!(std::str::from_utf8(&document).unwrap().contains(
+            "Content-Disposition: form-data; name=\"corro\"; filename=\"corro.svg\"; \
+             modification-date=\"Wed, 12 Feb 1997 16:29:51 GMT\"; size=6\r\n"
+        ));
     }
 }
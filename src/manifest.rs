@@ -0,0 +1,163 @@
+#[cfg(feature = "manifest")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "manifest")]
+use std::io::{Result, Write};
+
+/// A record of one part of a document, from [`FormData::manifest`][crate::FormData::manifest].
+///
+/// Field values and file contents are not included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ManifestEntry {
+    /// The part's name.
+    pub name: String,
+    /// The part's filename, if it has one.
+    pub filename: Option<String>,
+    /// The part's content type, if it has one.
+    pub content_type: Option<String>,
+    /// The size of the part's body. This is 0 for a part that didn't finish writing.
+    pub size: u64,
+    /// Where the part's body starts, counting from the start of the document.
+    pub offset: u64,
+    /// The SHA-256 hash of the part's body, in lowercase hex. Bodies are only hashed while a
+    /// manifest part is set (see
+    /// [`FormData::set_manifest_part`][crate::FormData::set_manifest_part]), so this is `None`
+    /// for parts written before that and for parts that didn't finish writing.
+    #[cfg(feature = "manifest")]
+    pub sha256: Option<String>,
+}
+
+/// Passes a part's body through to the document, hashing it on the way.
+#[cfg(feature = "manifest")]
+pub(crate) struct HashingWriter<'a, W> {
+    pub(crate) writer: &'a mut W,
+    pub(crate) sha256: &'a mut Sha256,
+}
+
+#[cfg(feature = "manifest")]
+impl<W: Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.sha256.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Finishes a body's hash, in lowercase hex.
+#[cfg(feature = "manifest")]
+pub(crate) fn hex(sha256: Sha256) -> String {
+    use std::fmt::Write;
+
+    let mut hex = String::with_capacity(64);
+    for byte in sha256.finalize() {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Renders `entries` as a JSON array of objects, for
+/// [`FormData::set_manifest_part`][crate::FormData::set_manifest_part].
+#[cfg(feature = "manifest")]
+pub(crate) fn to_json(entries: &[ManifestEntry]) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::from("[");
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"name\":");
+        write_json_str(&mut out, Some(&entry.name));
+        out.push_str(",\"filename\":");
+        write_json_str(&mut out, entry.filename.as_deref());
+        out.push_str(",\"content_type\":");
+        write_json_str(&mut out, entry.content_type.as_deref());
+        let _ = write!(out, ",\"size\":{}", entry.size);
+        out.push_str(",\"sha256\":");
+        write_json_str(&mut out, entry.sha256.as_deref());
+        out.push('}');
+    }
+    out.push(']');
+    out
+}
+
+#[cfg(feature = "manifest")]
+fn write_json_str(out: &mut String, s: Option<&str>) {
+    use std::fmt::Write;
+
+    let Some(s) = s else {
+        out.push_str("null");
+        return;
+    };
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(all(test, feature = "manifest"))]
+mod tests {
+    use super::{hex, to_json, HashingWriter, ManifestEntry};
+    use sha2::{Digest, Sha256};
+    use std::io::Write;
+
+    #[test]
+    fn json() {
+        let entries = [
+            ManifestEntry {
+                name: "cute".to_owned(),
+                filename: None,
+                content_type: None,
+                size: 3,
+                offset: 80,
+                sha256: None,
+            },
+            ManifestEntry {
+                name: "\"corro\"\n\u{1}".to_owned(),
+                filename: Some("corro.svg".to_owned()),
+                content_type: Some("image/svg+xml".to_owned()),
+                size: 7576,
+                offset: 200,
+                sha256: Some("0123abcd".to_owned()),
+            },
+        ];
+        assert_eq!(
+            to_json(&entries),
+            "[{\"name\":\"cute\",\"filename\":null,\"content_type\":null,\"size\":3,\
+             \"sha256\":null},{\"name\":\"\\\"corro\\\"\\n\\u0001\",\"filename\":\"corro.svg\",\
+             \"content_type\":\"image/svg+xml\",\"size\":7576,\"sha256\":\"0123abcd\"}]"
+        );
+    }
+
+    #[test]
+    fn hashing_writer() {
+        let mut document = Vec::new();
+        let mut sha256 = Sha256::new();
+        let mut writer = HashingWriter {
+            writer: &mut document,
+            sha256: &mut sha256,
+        };
+        writer.write_all(b"y").unwrap();
+        writer.write_all(b"es").unwrap();
+        assert_eq!(document, b"yes");
+        assert_eq!(
+            hex(sha256),
+            "8a798890fe93817163b10b5f7bd2ca4d25d84c52739a645a889c173eee7d9d3d"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+/// Hooks for feeding upload sizes and timings into a metrics system.
+///
+/// Set with [`FormData::set_metrics`][crate::FormData::set_metrics]. Both methods do nothing by
+/// default, so implementations only need to provide the ones they care about.
+///
+/// ```
+/// use form_data_builder::{FormData, Metrics};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// #[derive(Default)]
+/// struct Counter(AtomicU64);
+///
+/// impl Metrics for Counter {
+///     fn part_written(&self, _name: &str, bytes: u64, _elapsed: Duration) {
+///         self.0.fetch_add(bytes, Ordering::Relaxed);
+///     }
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let counter = Arc::new(Counter::default());
+/// let mut form = FormData::new(Vec::new());
+/// form.set_metrics(counter.clone());
+/// form.write_field("cute", "yes")?;
+/// assert_eq!(counter.0.load(Ordering::Relaxed), 3);
+/// # form.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Metrics {
+    /// Called after each part is written, with the size of its body and how long writing the
+    /// whole part took.
+    fn part_written(&self, name: &str, bytes: u64, elapsed: Duration) {
+        let _ = (name, bytes, elapsed);
+    }
+
+    /// Called when the document is finished, with its total size and how long it took since the
+    /// first part started.
+    fn finished(&self, bytes: u64, elapsed: Duration) {
+        let _ = (bytes, elapsed);
+    }
+}
+
+impl<M: Metrics + ?Sized> Metrics for std::sync::Arc<M> {
+    fn part_written(&self, name: &str, bytes: u64, elapsed: Duration) {
+        (**self).part_written(name, bytes, elapsed);
+    }
+
+    fn finished(&self, bytes: u64, elapsed: Duration) {
+        (**self).finished(bytes, elapsed);
+    }
+}
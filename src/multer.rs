@@ -0,0 +1,174 @@
+//! [multer] interop, for testing servers.
+//!
+//! [`body`] writes a list of [`Part`]s to a document and returns the boundary and the document
+//! as a stream of [`Bytes`], which are what [`multer::Multipart::new`] takes. A server's handler
+//! tests can then feed it documents written by this crate. [`multipart`] does both steps.
+//!
+//! [multer]: https://docs.rs/multer
+
+use crate::{FormData, Part};
+use bytes::Bytes;
+use futures_util::Stream;
+use std::num::NonZeroUsize;
+
+/// Writes `parts` to a document, returning its boundary and the document as a stream of chunks
+/// of at most `chunk_size` bytes.
+///
+/// Small chunks exercise a parser's handling of boundaries and headers split across reads.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{FormDataTemplate, Part};
+/// use std::num::NonZeroUsize;
+///
+/// let mut template = FormDataTemplate::new();
+/// template.push(Part::text("cute", "yes"));
+/// let (boundary, stream) =
+///     form_data_builder::multer::body(template.parts(), NonZeroUsize::new(7).unwrap())?;
+/// let multipart = multer::Multipart::new(stream, boundary);
+/// // ... pass `multipart` to the handler under test ...
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if a part is read from a file that cannot be opened.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`FormData::new`].
+pub fn body(
+    parts: &[Part<'_>],
+    chunk_size: NonZeroUsize,
+) -> std::io::Result<(
+    String,
+    impl Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+)> {
+    body_with_chunk_sizes(parts, move |_| chunk_size)
+}
+
+/// Like [`body`], but asks `next_chunk_size` for the size of each chunk, passing it how many
+/// bytes of the document came before the chunk.
+///
+/// This can mimic a transport whose chunk sizes vary, such as HTTP/2 frames, or follow a
+/// bandwidth measurement.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::Part;
+/// use std::num::NonZeroUsize;
+///
+/// // a small first chunk, then larger ones
+/// let (boundary, stream) = form_data_builder::multer::body_with_chunk_sizes(
+///     &[Part::text("cute", "yes")],
+///     |offset| NonZeroUsize::new(if offset == 0 { 3 } else { 16_384 }).unwrap(),
+/// )?;
+/// let multipart = multer::Multipart::new(stream, boundary);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if a part is read from a file that cannot be opened.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`FormData::new`].
+pub fn body_with_chunk_sizes<F>(
+    parts: &[Part<'_>],
+    mut next_chunk_size: F,
+) -> std::io::Result<(
+    String,
+    impl Stream<Item = std::io::Result<Bytes>> + Send + 'static,
+)>
+where
+    F: FnMut(usize) -> NonZeroUsize + Send + 'static,
+{
+    let mut form = FormData::new(Vec::new());
+    let boundary = form.boundary().to_owned();
+    let result = parts.iter().try_for_each(|part| form.write_part(part));
+    let document = Bytes::from(form.finish_or_abort(result)?);
+    let mut start = 0;
+    let chunks = std::iter::from_fn(move || {
+        if start == document.len() {
+            return None;
+        }
+        let end = document
+            .len()
+            .min(start.saturating_add(next_chunk_size(start).get()));
+        let chunk = document.slice(start..end);
+        start = end;
+        Some(Ok(chunk))
+    });
+    Ok((boundary, futures_util::stream::iter(chunks)))
+}
+
+/// Writes `parts` to a document and returns it as a [`multer::Multipart`], as a handler built on
+/// multer would receive it.
+///
+/// # Errors
+///
+/// Returns an error if a part is read from a file that cannot be opened.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`FormData::new`].
+pub fn multipart(parts: &[Part<'_>]) -> std::io::Result<::multer::Multipart<'static>> {
+    let (boundary, stream) = body(parts, NonZeroUsize::MAX)?;
+    Ok(::multer::Multipart::new(stream, boundary))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Part;
+    use std::num::NonZeroUsize;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let parts = [
+            Part::text("cute", "yes"),
+            Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+        ];
+        let (boundary, stream) = super::body(&parts, NonZeroUsize::new(5).unwrap()).unwrap();
+        let mut multipart = ::multer::Multipart::new(stream, boundary);
+        for part in &parts {
+            let field = multipart.next_field().await.unwrap().unwrap();
+            assert_eq!(field.name(), Some(part.name()));
+            assert_eq!(
+                field.content_type().map(ToString::to_string).as_deref(),
+                part.content_type()
+            );
+            let expected = match part.file_path() {
+                Some(path) => std::fs::read(path).unwrap(),
+                None => part.body().unwrap().to_vec(),
+            };
+            assert_eq!(field.bytes().await.unwrap(), expected);
+        }
+        assert!(multipart.next_field().await.unwrap().is_none());
+
+        let (_, stream) = super::body_with_chunk_sizes(&parts, |offset| {
+            NonZeroUsize::new(offset % 7 + 1).unwrap()
+        })
+        .unwrap();
+        let chunks: Vec<_> = futures_util::TryStreamExt::try_collect(stream)
+            .await
+            .unwrap();
+        let sizes: Vec<_> = chunks.iter().map(bytes::Bytes::len).collect();
+        assert_eq!(sizes[..3], [1, 2, 4]);
+
+        let mut multipart = super::multipart(&parts).unwrap();
+        assert_eq!(
+            multipart
+                .next_field()
+                .await
+                .unwrap()
+                .unwrap()
+                .text()
+                .await
+                .unwrap(),
+            "yes"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use serde_json::Value;
+
+/// How [`FormData::write_nested`][crate::FormData::write_nested] names the fields inside nested
+/// values. Each example is the name of the field for `1` in `{"a": {"b": [1]}}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NestedNaming {
+    /// `a[b][0]`, which PHP and Express's `qs` parser read back into nested values.
+    #[default]
+    Brackets,
+    /// `a[b][]`, leaving out array indices, which Rails reads back into arrays. Arrays of
+    /// objects or arrays can't be read back unambiguously this way.
+    EmptyBrackets,
+    /// `a.b.0`.
+    Dots,
+}
+
+impl NestedNaming {
+    /// Appends the name for `key` inside the value called `name` to `name`.
+    fn push(self, name: &mut String, key: Key<'_>) {
+        match (self, key) {
+            (NestedNaming::EmptyBrackets, Key::Index(_)) => name.push_str("[]"),
+            (NestedNaming::Brackets | NestedNaming::EmptyBrackets, key) => {
+                name.push('[');
+                key.push_to(name);
+                name.push(']');
+            }
+            (NestedNaming::Dots, key) => {
+                name.push('.');
+                key.push_to(name);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Key<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+impl Key<'_> {
+    fn push_to(self, name: &mut String) {
+        match self {
+            Key::Field(field) => name.push_str(field),
+            Key::Index(index) => name.push_str(&index.to_string()),
+        }
+    }
+}
+
+/// Flattens `value` into `(name, value)` fields, where `name` is the name of `value` itself.
+/// Nulls are left out.
+pub(crate) fn flatten(
+    value: &Value,
+    naming: NestedNaming,
+    name: &mut String,
+) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    flatten_into(value, naming, name, &mut fields);
+    fields
+}
+
+fn flatten_into(
+    value: &Value,
+    naming: NestedNaming,
+    name: &mut String,
+    fields: &mut Vec<(String, String)>,
+) {
+    let nested = |name: &mut String, key: Key<'_>, value: &Value, fields: &mut Vec<_>| {
+        let len = name.len();
+        naming.push(name, key);
+        flatten_into(value, naming, name, fields);
+        name.truncate(len);
+    };
+    match value {
+        Value::Null => {}
+        Value::Bool(value) => fields.push((name.clone(), value.to_string())),
+        Value::Number(value) => fields.push((name.clone(), value.to_string())),
+        Value::String(value) => fields.push((name.clone(), value.clone())),
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                nested(name, Key::Index(index), value, fields);
+            }
+        }
+        Value::Object(values) => {
+            for (field, value) in values {
+                nested(name, Key::Field(field), value, fields);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flatten, NestedNaming};
+
+    #[test]
+    fn flatten_naming() {
+        let value = serde_json::json!({
+            "b": [1, {"c": true}],
+            "d": null,
+            "e": "crab",
+        });
+        for (naming, expected) in [
+            (NestedNaming::Brackets, ["a[b][0]", "a[b][1][c]", "a[e]"]),
+            (NestedNaming::EmptyBrackets, ["a[b][]", "a[b][][c]", "a[e]"]),
+            (NestedNaming::Dots, ["a.b.0", "a.b.1.c", "a.e"]),
+        ] {
+            let fields = flatten(&value, naming, &mut "a".to_owned());
+            let names: Vec<_> = fields.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(names, expected);
+            let values: Vec<_> = fields.iter().map(|(_, value)| value.as_str()).collect();
+            assert_eq!(values, ["1", "true", "crab"]);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{FileTypePolicy, FilenameEncoding, PartHeader};
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Settings for how a [`FormData`][crate::FormData] writes its document, for
+/// [`FormData::with_options`][crate::FormData::with_options] and
+/// [`FormData::set_options`][crate::FormData::set_options].
+///
+/// Each field can also be set on its own with the matching `FormData::set_*` method. New fields
+/// may be added in any release, so start from [`FormDataOptions::default`]:
+///
+/// ```
+/// use form_data_builder::{FilenameEncoding, FormData, FormDataOptions};
+/// use std::time::Duration;
+///
+/// let mut options = FormDataOptions::default();
+/// options.time_limit = Some(Duration::from_secs(30));
+/// options.filename_encoding = FilenameEncoding::PercentEncode;
+/// let mut form = FormData::with_options(Vec::new(), options);
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+#[allow(clippy::struct_excessive_bools)] // independent on/off settings, not a state machine
+pub struct FormDataOptions {
+    /// See [`FormData::set_cancellation`][crate::FormData::set_cancellation].
+    pub cancellation: Option<Arc<AtomicBool>>,
+    /// See [`FormData::set_time_limit`][crate::FormData::set_time_limit].
+    pub time_limit: Option<Duration>,
+    /// See [`FormData::set_rate_limit`][crate::FormData::set_rate_limit].
+    pub rate_limit: Option<NonZeroU64>,
+    /// See [`FormData::set_read_ahead`][crate::FormData::set_read_ahead].
+    pub read_ahead: bool,
+    /// See [`FormData::set_flush_per_part`][crate::FormData::set_flush_per_part].
+    pub flush_per_part: bool,
+    /// See [`FormData::set_filename_encoding`][crate::FormData::set_filename_encoding].
+    pub filename_encoding: FilenameEncoding,
+    /// See [`FormData::set_file_type_policy`][crate::FormData::set_file_type_policy].
+    pub file_type_policy: FileTypePolicy,
+    /// See [`FormData::set_file_metadata`][crate::FormData::set_file_metadata].
+    pub file_metadata: bool,
+    /// See [`FormData::set_duplicate_policy`][crate::FormData::set_duplicate_policy].
+    #[cfg(feature = "dedup")]
+    pub duplicate_policy: crate::DuplicatePolicy,
+    /// See [`FormData::set_content_md5`][crate::FormData::set_content_md5].
+    #[cfg(feature = "content-md5")]
+    pub content_md5: bool,
+    /// See [`FormData::set_normalize_newlines`][crate::FormData::set_normalize_newlines].
+    pub normalize_newlines: bool,
+    /// See [`FormData::set_strict_names`][crate::FormData::set_strict_names].
+    pub strict_names: bool,
+    /// See [`FormData::set_header_order`][crate::FormData::set_header_order].
+    pub header_order: Vec<PartHeader>,
+    /// See [`FormData::set_canonical`][crate::FormData::set_canonical].
+    pub canonical: bool,
+    /// See [`FormData::set_manifest_part`][crate::FormData::set_manifest_part].
+    #[cfg(feature = "manifest")]
+    pub manifest_part: Option<String>,
+    /// The size of the buffer that files are read through. Defaults to 64 KiB.
+    pub file_buffer_size: usize,
+    /// Content types for [`FormData::write_path_auto`][crate::FormData::write_path_auto], keyed
+    /// by lowercase file extension without the leading dot. These take precedence over the
+    /// built-in table.
+    pub content_types: HashMap<String, String>,
+}
+
+impl Default for FormDataOptions {
+    fn default() -> FormDataOptions {
+        FormDataOptions {
+            cancellation: None,
+            time_limit: None,
+            rate_limit: None,
+            read_ahead: false,
+            flush_per_part: false,
+            filename_encoding: FilenameEncoding::default(),
+            file_type_policy: FileTypePolicy::default(),
+            file_metadata: false,
+            #[cfg(feature = "dedup")]
+            duplicate_policy: crate::DuplicatePolicy::default(),
+            #[cfg(feature = "content-md5")]
+            content_md5: false,
+            normalize_newlines: false,
+            strict_names: false,
+            header_order: Vec::new(),
+            canonical: false,
+            #[cfg(feature = "manifest")]
+            manifest_part: None,
+            file_buffer_size: 64 * 1024,
+            content_types: HashMap::new(),
+        }
+    }
+}
+
+/// Content types for common file extensions, used when [`FormDataOptions::content_types`] has
+/// no entry.
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("webp", "image/webp"),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+];
+
+impl FormDataOptions {
+    /// The content type for a file at `path`, from its extension.
+    pub(crate) fn content_type_for(&self, path: &Path) -> &str {
+        let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+            return "application/octet-stream";
+        };
+        let extension = extension.to_ascii_lowercase();
+        if let Some(content_type) = self.content_types.get(&extension) {
+            return content_type;
+        }
+        CONTENT_TYPES
+            .iter()
+            .find(|(known, _)| *known == extension)
+            .map_or("application/octet-stream", |(_, content_type)| content_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormDataOptions;
+    use std::path::Path;
+
+    #[test]
+    fn content_type_for() {
+        let mut options = FormDataOptions::default();
+        options
+            .content_types
+            .insert("corro".to_owned(), "application/x-corro".to_owned());
+        options
+            .content_types
+            .insert("png".to_owned(), "image/x-ferris".to_owned());
+        for (path, content_type) in [
+            ("corro.svg", "image/svg+xml"),
+            ("CORRO.SVG", "image/svg+xml"),
+            ("crab.corro", "application/x-corro"),
+            ("ferris.png", "image/x-ferris"),
+            ("notes.unknown", "application/octet-stream"),
+            ("Makefile", "application/octet-stream"),
+        ] {
+            assert_eq!(options.content_type_for(Path::new(path)), content_type);
+        }
+    }
+}
@@ -0,0 +1,773 @@
+//! Reading `multipart/form-data` documents back.
+//!
+//! This is a small, buffered parser: the whole document is held in memory. It exists so that
+//! documents produced by [`FormData`][crate::FormData] (or sent by a browser) can be inspected,
+//! tested, and saved, not to be a general-purpose streaming multipart server.
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use form_data_builder::{parse::FormDataParts, FormData};
+//!
+//! let mut form = FormData::new(Vec::new());
+//! let content_type = form.content_type_header();
+//! form.write_field("cute", "yes")?;
+//! let document = form.finish()?;
+//!
+//! let parts = FormDataParts::parse_with_content_type(&document, &content_type)?;
+//! assert_eq!(parts.get("cute").unwrap().body(), b"yes");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::OpenOptions;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+
+/// A single part of a parsed `multipart/form-data` document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedPart {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl ParsedPart {
+    /// The `name` parameter of the part's `Content-Disposition` header.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The `filename` parameter of the part's `Content-Disposition` header, if present.
+    #[must_use]
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// The value of the part's `Content-Type` header, if present.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// All of the part's headers, in the order they appeared.
+    #[must_use]
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// The body of the part.
+    #[must_use]
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Consumes the part, returning its body.
+    #[must_use]
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Returns `true` if the part has a `filename` parameter, as file fields do.
+    #[must_use]
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+}
+
+/// The parts of a parsed `multipart/form-data` document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormDataParts {
+    boundary: String,
+    parts: Vec<ParsedPart>,
+}
+
+impl FormDataParts {
+    /// Parses `document`, which is delimited by `boundary`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidData`] if the document is malformed.
+    pub fn parse(document: &[u8], boundary: &str) -> Result<FormDataParts> {
+        FormDataParts::parse_document(document, boundary, false)
+    }
+
+    fn parse_document(document: &[u8], boundary: &str, strict: bool) -> Result<FormDataParts> {
+        let delimiter = [b"--", boundary.as_bytes()].concat();
+        let body_end = [b"\r\n--", boundary.as_bytes()].concat();
+
+        let mut rest = match find(document, &delimiter) {
+            Some(0) => &document[delimiter.len()..],
+            Some(_) if strict => return Err(invalid("document has a preamble")),
+            Some(index) => &document[index + delimiter.len()..],
+            None => return Err(invalid("missing opening boundary")),
+        };
+
+        let mut parts = Vec::new();
+        loop {
+            if let Some(epilogue) = rest.strip_prefix(b"--") {
+                if strict && epilogue != b"\r\n" {
+                    return Err(invalid("closing boundary is not followed by exactly CRLF"));
+                }
+                break;
+            }
+            rest = rest
+                .strip_prefix(b"\r\n")
+                .ok_or_else(|| invalid("boundary line is not terminated with CRLF"))?;
+
+            // a part with no headers at all starts directly with the blank line
+            let (header_block, after_headers) = if let Some(body) = rest.strip_prefix(b"\r\n") {
+                (&rest[..0], body)
+            } else {
+                let header_end = find(rest, b"\r\n\r\n")
+                    .ok_or_else(|| invalid("part headers are not terminated"))?;
+                (&rest[..header_end], &rest[header_end + 4..])
+            };
+
+            let end = find(after_headers, &body_end)
+                .ok_or_else(|| invalid("missing closing boundary"))?;
+            parts.push(parse_part(header_block, &after_headers[..end], strict)?);
+            rest = &after_headers[end + body_end.len()..];
+        }
+
+        Ok(FormDataParts {
+            boundary: boundary.to_owned(),
+            parts,
+        })
+    }
+
+    /// Parses `document`, taking the boundary from the value of a `Content-Type` header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidData`] if `content_type` is not a
+    /// `multipart/form-data` type with a boundary, or if the document is malformed.
+    pub fn parse_with_content_type(document: &[u8], content_type: &str) -> Result<FormDataParts> {
+        let boundary = boundary_from_content_type(content_type)
+            .ok_or_else(|| invalid("not a multipart/form-data content type with a boundary"))?;
+        FormDataParts::parse(document, &boundary)
+    }
+
+    /// The boundary the document was delimited by.
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Returns the first part named `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ParsedPart> {
+        self.parts.iter().find(|part| part.name == name)
+    }
+
+    /// Returns an iterator over the parts, in document order.
+    pub fn iter(&self) -> std::slice::Iter<'_, ParsedPart> {
+        self.parts.iter()
+    }
+
+    /// The number of parts in the document.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Returns `true` if the document has no parts.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Returns `true` if both documents have the same parts, with the same headers in the same
+    /// order, whatever their boundaries. See also [`same_document`].
+    #[must_use]
+    pub fn eq_ignore_boundary(&self, other: &FormDataParts) -> bool {
+        self.parts == other.parts
+    }
+
+    /// Like [`FormDataParts::eq_ignore_boundary`], but also ignores the order of each part's
+    /// headers and the case of their names.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use form_data_builder::{parse::FormDataParts, FormData, PartHeader};
+    ///
+    /// let mut written = Vec::new();
+    /// for order in [&[][..], &[PartHeader::ContentType]] {
+    ///     let mut form = FormData::new(Vec::new());
+    ///     form.set_header_order(order);
+    ///     let boundary = form.boundary().to_owned();
+    ///     form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    ///     written.push(FormDataParts::parse(&form.finish()?, &boundary)?);
+    /// }
+    /// assert!(!written[0].eq_ignore_boundary(&written[1]));
+    /// assert!(written[0].eq_ignore_header_order(&written[1]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn eq_ignore_header_order(&self, other: &FormDataParts) -> bool {
+        fn sorted_headers(part: &ParsedPart) -> Vec<(String, &str)> {
+            let mut headers: Vec<_> = part
+                .headers
+                .iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), value.as_str()))
+                .collect();
+            headers.sort_unstable();
+            headers
+        }
+
+        self.parts.len() == other.parts.len()
+            && self.parts.iter().zip(&other.parts).all(|(a, b)| {
+                a.name == b.name
+                    && a.filename == b.filename
+                    && a.content_type == b.content_type
+                    && a.body == b.body
+                    && sorted_headers(a) == sorted_headers(b)
+            })
+    }
+
+    /// Writes every file part into `dir` and collects the text fields.
+    ///
+    /// File names are taken from the `filename` parameter and reduced to their final path
+    /// component. Anything other than ASCII letters, digits, `.`, `-`, and `_` is replaced with
+    /// `_`, and leading dots are dropped; a name left empty becomes `file`.
+    /// Existing files are never overwritten; a numeric suffix is added instead.
+    ///
+    /// Limits and text fields are checked before anything is written, so a document that is too
+    /// big or has a text field that isn't valid UTF-8 leaves `dir` untouched.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use form_data_builder::parse::{FormDataParts, SaveLimits};
+    /// # let dir = std::env::temp_dir().join(format!("fdb-doc-save-{}", std::process::id()));
+    /// # std::fs::create_dir_all(&dir)?;
+    /// # let mut form = form_data_builder::FormData::new(Vec::new());
+    /// # let boundary = form.boundary().to_owned();
+    /// # form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+    /// # form.write_field("cute", "yes")?;
+    /// # let document = form.finish()?;
+    ///
+    /// let parts = FormDataParts::parse(&document, &boundary)?;
+    /// let limits = SaveLimits {
+    ///     max_file_size: Some(1 << 20),
+    ///     ..SaveLimits::default()
+    /// };
+    /// let saved = parts.save_to_dir(&dir, &limits)?;
+    /// assert_eq!(saved.files[0].path, dir.join("corro.svg"));
+    /// assert_eq!(saved.fields, [("cute".to_owned(), "yes".to_owned())]);
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a limit is exceeded, if a text field is not valid UTF-8, or if
+    /// writing a file fails.
+    pub fn save_to_dir<P: AsRef<Path>>(&self, dir: P, limits: &SaveLimits) -> Result<SavedForm> {
+        let mut total = 0;
+        for part in self.parts.iter().filter(|part| part.is_file()) {
+            let size = part.body.len() as u64;
+            if limits.max_file_size.is_some_and(|max| size > max) {
+                return Err(Error::other(format!(
+                    "file part {:?} exceeds the per-file size limit",
+                    part.name
+                )));
+            }
+            total += size;
+        }
+        if limits.max_total_size.is_some_and(|max| total > max) {
+            return Err(Error::other("file parts exceed the total size limit"));
+        }
+
+        let mut saved = SavedForm::default();
+        for part in self.parts.iter().filter(|part| !part.is_file()) {
+            let value = String::from_utf8(part.body.clone())
+                .map_err(|_| invalid(format!("text field {:?} is not valid UTF-8", part.name)))?;
+            saved.fields.push((part.name.clone(), value));
+        }
+        for part in &self.parts {
+            if let Some(filename) = &part.filename {
+                let path = create_unique(dir.as_ref(), &sanitize_filename(filename), &part.body)?;
+                saved.files.push(SavedFile {
+                    name: part.name.clone(),
+                    filename: filename.clone(),
+                    content_type: part.content_type.clone(),
+                    path,
+                    size: part.body.len() as u64,
+                });
+            }
+        }
+        Ok(saved)
+    }
+}
+
+impl IntoIterator for FormDataParts {
+    type Item = ParsedPart;
+    type IntoIter = std::vec::IntoIter<ParsedPart>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.parts.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FormDataParts {
+    type Item = &'a ParsedPart;
+    type IntoIter = std::slice::Iter<'a, ParsedPart>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.parts.iter()
+    }
+}
+
+/// Parses `document` and saves it with [`FormDataParts::save_to_dir`].
+///
+/// # Errors
+///
+/// Returns an error if the document is malformed or if saving it fails.
+pub fn save_to_dir<P: AsRef<Path>>(
+    document: &[u8],
+    boundary: &str,
+    dir: P,
+    limits: &SaveLimits,
+) -> Result<SavedForm> {
+    FormDataParts::parse(document, boundary)?.save_to_dir(dir, limits)
+}
+
+/// Checks that `document` is a well-formed `multipart/form-data` document delimited by
+/// `boundary`, as [`FormData`][crate::FormData] produces.
+///
+/// This is stricter than [`FormDataParts::parse`]: the boundary must be valid per [RFC 2046 §
+/// 5.1.1][rfc2046], there must be no preamble, the closing boundary must be the last line of the
+/// document, and header names must be valid tokens. Every part must be terminated and have a
+/// `form-data` `Content-Disposition` with a `name`.
+///
+/// [rfc2046]: https://www.rfc-editor.org/rfc/rfc2046.html#section-5.1.1
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{parse::verify, FormData};
+///
+/// let mut form = FormData::new(Vec::new());
+/// let boundary = form.boundary().to_owned();
+/// form.write_field("cute", "yes")?;
+/// let document = form.finish()?;
+///
+/// verify(&document, &boundary)?;
+/// assert!(verify(&document[..document.len() - 4], &boundary).is_err());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error of kind [`ErrorKind::InvalidData`] describing the first problem found.
+pub fn verify(document: &[u8], boundary: &str) -> Result<()> {
+    if !is_valid_boundary(boundary) {
+        return Err(invalid("boundary is not valid"));
+    }
+    FormDataParts::parse_document(document, boundary, true).map(drop)
+}
+
+/// Returns `true` if documents `a` and `b` have the same parts, like
+/// [`FormDataParts::eq_ignore_boundary`], taking each one's boundary from its first line. This is
+/// for comparing a document against a golden file in tests, where the boundary is random.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{parse::same_document, FormData};
+///
+/// let golden = b"--old\r\n\
+///     Content-Disposition: form-data; name=\"cute\"\r\n\
+///     \r\n\
+///     yes\r\n\
+///     --old--\r\n";
+/// let mut form = FormData::new(Vec::new());
+/// form.write_field("cute", "yes")?;
+/// assert!(same_document(golden, &form.finish()?)?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error of kind [`ErrorKind::InvalidData`] if either document is malformed or doesn't
+/// start with a boundary line.
+pub fn same_document(a: &[u8], b: &[u8]) -> Result<bool> {
+    let parse = |document: &[u8]| {
+        let boundary = document
+            .strip_prefix(b"--")
+            .and_then(|rest| rest.split(|&b| b == b'\r').next())
+            .and_then(|boundary| std::str::from_utf8(boundary).ok())
+            .ok_or_else(|| invalid("document doesn't start with a boundary line"))?;
+        FormDataParts::parse(document, boundary)
+    };
+    Ok(parse(a)?.eq_ignore_boundary(&parse(b)?))
+}
+
+/// Size limits for [`FormDataParts::save_to_dir`]. `None` means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveLimits {
+    /// The largest a single file part may be, in bytes.
+    pub max_file_size: Option<u64>,
+    /// The largest all file parts may be together, in bytes.
+    pub max_total_size: Option<u64>,
+}
+
+/// The result of [`FormDataParts::save_to_dir`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavedForm {
+    /// The file parts that were written, in document order.
+    pub files: Vec<SavedFile>,
+    /// The `(name, value)` pairs of the text fields, in document order.
+    pub fields: Vec<(String, String)>,
+}
+
+/// A file part written by [`FormDataParts::save_to_dir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedFile {
+    /// The name of the field.
+    pub name: String,
+    /// The `filename` parameter as sent, before sanitization.
+    pub filename: String,
+    /// The part's `Content-Type`, if it had one.
+    pub content_type: Option<String>,
+    /// Where the file was written.
+    pub path: PathBuf,
+    /// The size of the file, in bytes.
+    pub size: u64,
+}
+
+/// Extracts the boundary parameter from a `multipart/form-data` `Content-Type` header value.
+///
+/// ```
+/// use form_data_builder::parse::boundary_from_content_type;
+///
+/// assert_eq!(
+///     boundary_from_content_type("multipart/form-data; boundary=\"abc\"").as_deref(),
+///     Some("abc"),
+/// );
+/// assert_eq!(boundary_from_content_type("text/plain"), None);
+/// ```
+#[must_use]
+pub fn boundary_from_content_type(content_type: &str) -> Option<String> {
+    let (essence, params) = content_type.split_once(';')?;
+    if !essence.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    parse_params(params)
+        .into_iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+pub(crate) fn parse_part(header_block: &[u8], body: &[u8], strict: bool) -> Result<ParsedPart> {
+    let header_block = std::str::from_utf8(header_block)
+        .map_err(|_| invalid("part headers are not valid UTF-8"))?;
+
+    let mut headers = Vec::new();
+    for line in header_block.split("\r\n").filter(|line| !line.is_empty()) {
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid(format!("malformed part header {line:?}")))?;
+        if strict && !is_token(key) {
+            return Err(invalid(format!("invalid part header name {key:?}")));
+        }
+        headers.push((key.trim().to_owned(), value.trim().to_owned()));
+    }
+
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let disposition =
+        header("Content-Disposition").ok_or_else(|| invalid("part has no Content-Disposition"))?;
+    let (kind, params) = disposition.split_once(';').unwrap_or((disposition, ""));
+    if !kind.trim().eq_ignore_ascii_case("form-data") {
+        return Err(invalid("part disposition is not form-data"));
+    }
+    let params = parse_params(params);
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+
+    Ok(ParsedPart {
+        name: param("name").ok_or_else(|| invalid("part has no name"))?,
+        filename: param("filename"),
+        content_type: header("Content-Type").map(str::to_owned),
+        body: body.to_vec(),
+        headers,
+    })
+}
+
+/// Rewrites `content_type` for [`FormData::set_canonical`][crate::FormData::set_canonical]: the
+/// type and parameter names lowercased, parameters sorted by name and separated by `"; "`, and
+/// values quoted only if they aren't tokens.
+pub(crate) fn canonical_content_type(content_type: &str) -> String {
+    let (essence, params) = content_type.split_once(';').unwrap_or((content_type, ""));
+    let mut params = parse_params(params);
+    for (key, _) in &mut params {
+        key.make_ascii_lowercase();
+    }
+    params.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut canonical = essence.trim().to_ascii_lowercase();
+    for (key, value) in params {
+        canonical.push_str("; ");
+        canonical.push_str(&key);
+        canonical.push('=');
+        if is_token(&value) {
+            canonical.push_str(&value);
+        } else {
+            canonical.push('"');
+            canonical.push_str(&value.replace('\\', "\\\\").replace('"', "\\\""));
+            canonical.push('"');
+        }
+    }
+    canonical
+}
+
+/// Parses `; key=value; key="quoted value"` parameter lists.
+fn parse_params(mut params: &str) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    loop {
+        params = params.trim_start_matches([';', ' ', '\t']);
+        let Some((key, rest)) = params.split_once('=') else {
+            return out;
+        };
+        let key = key.trim().to_owned();
+        let rest = rest.trim_start();
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                    '"' => {
+                        end = index + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            out.push((key, value));
+            params = &quoted[end..];
+        } else {
+            let end = rest.find(';').unwrap_or(rest.len());
+            out.push((key, rest[..end].trim().to_owned()));
+            params = &rest[end..];
+        }
+    }
+}
+
+fn sanitize_filename(filename: &str) -> String {
+    let base = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let clean: String = base
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    let clean = clean.trim_start_matches('.');
+    if clean.is_empty() {
+        "file".to_owned()
+    } else {
+        clean.to_owned()
+    }
+}
+
+fn create_unique(dir: &Path, filename: &str, contents: &[u8]) -> Result<PathBuf> {
+    let (stem, extension) = match filename.rfind('.') {
+        Some(index) => filename.split_at(index),
+        None => (filename, ""),
+    };
+    for n in 0.. {
+        let path = if n == 0 {
+            dir.join(filename)
+        } else {
+            dir.join(format!("{stem}-{n}{extension}"))
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(contents)?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!()
+}
+
+pub(crate) fn is_valid_boundary(boundary: &str) -> bool {
+    (1..=70).contains(&boundary.len())
+        && !boundary.ends_with(' ')
+        && boundary
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b" '()+_,-./:=?".contains(&b))
+}
+
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize_filename, FormDataParts, SaveLimits};
+
+    const FIREFOX: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testdata/form-data.bin"
+    ));
+    const BOUNDARY: &str = "---------------------------20598614689265574691413388431";
+
+    #[test]
+    fn parse_firefox() {
+        let parts = FormDataParts::parse(FIREFOX, BOUNDARY).unwrap();
+        let names: Vec<_> = parts.iter().map(super::ParsedPart::name).collect();
+        assert_eq!(names, ["file-a", "text-a", "file-b", "text-b"]);
+
+        let corro = parts.get("file-b").unwrap();
+        assert_eq!(corro.filename(), Some("corro.svg"));
+        assert_eq!(corro.content_type(), Some("image/svg+xml"));
+        assert_eq!(
+            corro.body(),
+            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/corro.svg"))
+        );
+        assert!(!parts.get("text-a").unwrap().is_file());
+    }
+
+    #[test]
+    fn parse_truncated() {
+        assert!(FormDataParts::parse(&FIREFOX[..FIREFOX.len() - 70], BOUNDARY).is_err());
+    }
+
+    #[test]
+    fn verify_strict() {
+        super::verify(FIREFOX, BOUNDARY).unwrap();
+
+        let mut preamble = b"hello\r\n".to_vec();
+        preamble.extend_from_slice(FIREFOX);
+        assert!(FormDataParts::parse(&preamble, BOUNDARY).is_ok());
+        assert!(super::verify(&preamble, BOUNDARY).is_err());
+
+        let mut epilogue = FIREFOX.to_vec();
+        epilogue.extend_from_slice(b"goodbye");
+        assert!(super::verify(&epilogue, BOUNDARY).is_err());
+
+        assert!(super::verify(FIREFOX, &BOUNDARY[1..]).is_err());
+    }
+
+    #[test]
+    fn same_document() {
+        let document = |boundary: &str, header: &str| {
+            format!(
+                "--{boundary}\r\n\
+                 Content-Disposition: form-data; name=\"a\"; filename=\"a.txt\"\r\n\
+                 {header}\r\n\
+                 \r\n\
+                 yes\r\n\
+                 --{boundary}--\r\n"
+            )
+        };
+        let a = document("one", "Content-Type: text/plain");
+        assert!(super::same_document(
+            a.as_bytes(),
+            document("two", "Content-Type: text/plain").as_bytes()
+        )
+        .unwrap());
+        assert!(!super::same_document(
+            a.as_bytes(),
+            document("two", "Content-Type: text/html").as_bytes()
+        )
+        .unwrap());
+        assert!(super::same_document(a.as_bytes(), b"preamble\r\n").is_err());
+
+        let parse =
+            |document: &str, boundary| FormDataParts::parse(document.as_bytes(), boundary).unwrap();
+        let reordered = "--b\r\n\
+                         content-type: text/plain\r\n\
+                         Content-Disposition: form-data; name=\"a\"; filename=\"a.txt\"\r\n\
+                         \r\n\
+                         yes\r\n\
+                         --b--\r\n";
+        assert!(!parse(&a, "one").eq_ignore_boundary(&parse(reordered, "b")));
+        assert!(parse(&a, "one").eq_ignore_header_order(&parse(reordered, "b")));
+        let changed = reordered.replace("yes", "no");
+        assert!(!parse(&a, "one").eq_ignore_header_order(&parse(&changed, "b")));
+    }
+
+    #[test]
+    fn canonical_content_type() {
+        for (content_type, expected) in [
+            ("text/plain", "text/plain"),
+            (
+                "Text/Plain ;Format=flowed;  charset=\"utf-8\"",
+                "text/plain; charset=utf-8; format=flowed",
+            ),
+            (
+                "application/json; profile=\"a b\"",
+                "application/json; profile=\"a b\"",
+            ),
+        ] {
+            assert_eq!(super::canonical_content_type(content_type), expected);
+        }
+    }
+
+    #[test]
+    fn sanitize() {
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename("C:\\evil\\..\\.bashrc"), "bashrc");
+        assert_eq!(sanitize_filename("naïve file.txt"), "na_ve_file.txt");
+        assert_eq!(sanitize_filename(".."), "file");
+    }
+
+    #[test]
+    fn save_limits() {
+        let parts = FormDataParts::parse(FIREFOX, BOUNDARY).unwrap();
+        let dir = std::env::temp_dir().join(format!("fdb-test-limits-{}", std::process::id()));
+        let limits = SaveLimits {
+            max_total_size: Some(100),
+            ..SaveLimits::default()
+        };
+        assert!(parts.save_to_dir(&dir, &limits).is_err());
+        assert!(!dir.exists());
+
+        let document = b"--b\r\n\
+                         Content-Disposition: form-data; name=\"corro\"; filename=\"corro.svg\"\r\n\
+                         \r\n\
+                         <svg/>\r\n\
+                         --b\r\n\
+                         Content-Disposition: form-data; name=\"cute\"\r\n\
+                         \r\n\
+                         \xff\r\n\
+                         --b--\r\n";
+        let parts = FormDataParts::parse(document, "b").unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(parts.save_to_dir(&dir, &SaveLimits::default()).is_err());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,478 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A description of a single part of a document, written later with
+/// [`FormData::write_part`][crate::FormData::write_part] or as part of a
+/// [`FormDataTemplate`][crate::FormDataTemplate].
+///
+/// ```
+/// use form_data_builder::Part;
+///
+/// let field = Part::text("cute", "yes");
+/// let file = Part::file("corro", include_bytes!("../testdata/corro.svg"), "image/svg+xml")
+///     .with_filename("corro.svg");
+/// let path = Part::path("ferris", "testdata/rustacean-flat-noshadow.png", "image/png");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Part<'a> {
+    name: Cow<'a, str>,
+    filename: Option<Cow<'a, str>>,
+    content_type: Option<Cow<'a, str>>,
+    body: Body<'a>,
+    sensitive: bool,
+    disposition: DispositionParams,
+}
+
+/// Optional `Content-Disposition` parameters from [RFC 2183 § 2][rfc2183].
+///
+/// [rfc2183]: https://www.rfc-editor.org/rfc/rfc2183.html#section-2
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct DispositionParams {
+    pub(crate) creation_date: Option<SystemTime>,
+    pub(crate) modification_date: Option<SystemTime>,
+    pub(crate) size: Option<u64>,
+}
+
+impl DispositionParams {
+    /// Appends the parameters that are set, each starting with `; `.
+    pub(crate) fn write(&self, out: &mut Vec<u8>) {
+        for (param, date) in [
+            ("creation-date", self.creation_date),
+            ("modification-date", self.modification_date),
+        ] {
+            if let Some(date) = date {
+                out.extend_from_slice(b"; ");
+                out.extend_from_slice(param.as_bytes());
+                out.extend_from_slice(b"=\"");
+                crate::date::write_rfc822(out, date);
+                out.push(b'"');
+            }
+        }
+        if let Some(size) = self.size {
+            out.extend_from_slice(b"; size=");
+            out.extend_from_slice(size.to_string().as_bytes());
+        }
+    }
+
+    /// Fills in `size` and `modification-date` from a file's metadata, keeping any that were set
+    /// explicitly.
+    pub(crate) fn or_metadata(self, metadata: &Metadata) -> DispositionParams {
+        DispositionParams {
+            modification_date: self.modification_date.or(metadata.modified().ok()),
+            size: self.size.or(Some(metadata.len())),
+            ..self
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum Body<'a> {
+    Bytes(Cow<'a, [u8]>),
+    #[cfg(feature = "bytes")]
+    Shared(bytes::Bytes),
+    /// Opened each time the part is written.
+    Path(Cow<'a, Path>),
+}
+
+impl<'a> Part<'a> {
+    /// Describes a non-file field.
+    ///
+    /// The name and value can be borrowed or owned:
+    ///
+    /// ```
+    /// use form_data_builder::Part;
+    ///
+    /// let name = String::from("cute");
+    /// let borrowed = Part::text(&name, "yes"); // borrows `name`
+    /// let owned = Part::text(format!("very {name}"), String::from("yes")); // no copies either
+    /// ```
+    pub fn text<N: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>>(name: N, value: V) -> Part<'a> {
+        Part {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            body: Body::Bytes(str_bytes(value.into())),
+            sensitive: false,
+            disposition: DispositionParams::default(),
+        }
+    }
+
+    /// Describes a non-file field whose value is sensitive. The `Debug` output and
+    /// [`FormData::describe`][crate::FormData::describe] show `[REDACTED]` in place of its value.
+    ///
+    /// With the `zeroize` feature enabled, the value is zeroed when the part is dropped, unless
+    /// it was borrowed.
+    pub fn secret<N: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>>(name: N, value: V) -> Part<'a> {
+        Part {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            body: Body::Bytes(str_bytes(value.into())),
+            sensitive: true,
+            disposition: DispositionParams::default(),
+        }
+    }
+
+    /// Describes a file field without a `filename` parameter. Use [`Part::with_filename`] to add
+    /// one.
+    ///
+    /// The body can be borrowed, such as from the request a handler is responding to, or owned.
+    pub fn file<N: Into<Cow<'a, str>>, B: Into<Cow<'a, [u8]>>, C: Into<Cow<'a, str>>>(
+        name: N,
+        body: B,
+        content_type: C,
+    ) -> Part<'a> {
+        Part {
+            name: name.into(),
+            filename: None,
+            content_type: Some(content_type.into()),
+            body: Body::Bytes(body.into()),
+            sensitive: false,
+            disposition: DispositionParams::default(),
+        }
+    }
+
+    /// Describes a file field whose body is a [`bytes::Bytes`], without a `filename` parameter.
+    ///
+    /// Cloning the part (or a [`FormDataTemplate`][crate::FormDataTemplate] holding it) shares
+    /// the body rather than copying it, which suits templates that are re-emitted across retries
+    /// or threads.
+    ///
+    /// ```
+    /// use form_data_builder::Part;
+    ///
+    /// let body = bytes::Bytes::from_static(include_bytes!("../testdata/corro.svg"));
+    /// let part = Part::file_bytes("corro", body, "image/svg+xml");
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn file_bytes<N: Into<Cow<'a, str>>, C: Into<Cow<'a, str>>>(
+        name: N,
+        body: bytes::Bytes,
+        content_type: C,
+    ) -> Part<'a> {
+        Part {
+            name: name.into(),
+            filename: None,
+            content_type: Some(content_type.into()),
+            body: Body::Shared(body),
+            sensitive: false,
+            disposition: DispositionParams::default(),
+        }
+    }
+
+    /// Describes a file field whose body is the file at `path`, with the `filename` parameter
+    /// taken from `path`.
+    ///
+    /// The file is not opened until the part is written, and is opened again each time it is
+    /// written.
+    pub fn path<N: Into<Cow<'a, str>>, P: Into<PathBuf>, C: Into<Cow<'a, str>>>(
+        name: N,
+        path: P,
+        content_type: C,
+    ) -> Part<'a> {
+        Part::path_cow(name.into(), Cow::Owned(path.into()), content_type.into())
+    }
+
+    /// Like [`Part::path`], but borrows `path` rather than taking ownership of it.
+    ///
+    /// ```
+    /// use form_data_builder::Part;
+    /// use std::path::Path;
+    ///
+    /// fn upload_part(svg: &Path) -> Part<'_> {
+    ///     Part::borrowed_path("corro", svg, "image/svg+xml")
+    /// }
+    /// ```
+    pub fn borrowed_path<N: Into<Cow<'a, str>>, C: Into<Cow<'a, str>>>(
+        name: N,
+        path: &'a Path,
+        content_type: C,
+    ) -> Part<'a> {
+        Part::path_cow(name.into(), Cow::Borrowed(path), content_type.into())
+    }
+
+    fn path_cow(name: Cow<'a, str>, path: Cow<'a, Path>, content_type: Cow<'a, str>) -> Part<'a> {
+        Part {
+            name,
+            filename: path
+                .file_name()
+                .map(|filename| Cow::Owned(filename.to_string_lossy().into_owned())),
+            content_type: Some(content_type),
+            body: Body::Path(path),
+            sensitive: false,
+            disposition: DispositionParams::default(),
+        }
+    }
+
+    /// Sets the `filename` parameter.
+    #[must_use]
+    pub fn with_filename<F: Into<Cow<'a, str>>>(mut self, filename: F) -> Part<'a> {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Sets the `creation-date` parameter, which some document-management backends read.
+    ///
+    /// ```
+    /// use form_data_builder::Part;
+    /// use std::time::SystemTime;
+    ///
+    /// let part = Part::path("corro", "testdata/corro.svg", "image/svg+xml")
+    ///     .with_creation_date(SystemTime::UNIX_EPOCH)
+    ///     .with_modification_date(SystemTime::now())
+    ///     .with_size(7576);
+    /// ```
+    #[must_use]
+    pub fn with_creation_date(mut self, date: SystemTime) -> Part<'a> {
+        self.disposition.creation_date = Some(date);
+        self
+    }
+
+    /// Sets the `modification-date` parameter.
+    #[must_use]
+    pub fn with_modification_date(mut self, date: SystemTime) -> Part<'a> {
+        self.disposition.modification_date = Some(date);
+        self
+    }
+
+    /// Sets the `size` parameter, the size of the file in bytes. This is only informative: it is
+    /// not checked against the body.
+    #[must_use]
+    pub fn with_size(mut self, size: u64) -> Part<'a> {
+        self.disposition.size = Some(size);
+        self
+    }
+
+    /// Adds a parameter to the part's `Content-Type`, such as `charset` or a vendor parameter a
+    /// server asks for. The value is quoted if it needs to be.
+    ///
+    /// ```
+    /// # fn main() -> std::io::Result<()> {
+    /// use form_data_builder::Part;
+    ///
+    /// let part = Part::file("report", &b"{}"[..], "application/vnd.example+json")
+    ///     .with_content_type_param("version", "2")?
+    ///     .with_content_type_param("profile", "https://example.com/report; v2")?;
+    /// assert_eq!(
+    ///     part.content_type(),
+    ///     Some(concat!(
+    ///         "application/vnd.example+json; version=2; ",
+    ///         "profile=\"https://example.com/report; v2\"",
+    ///     )),
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidInput`][std::io::ErrorKind::InvalidInput] if
+    /// the part has no `Content-Type` (it is a non-file field), if `name` is not a valid
+    /// parameter name, or if `value` contains control characters.
+    pub fn with_content_type_param(mut self, name: &str, value: &str) -> std::io::Result<Part<'a>> {
+        let invalid =
+            |message: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, message);
+        let Some(content_type) = &mut self.content_type else {
+            return Err(invalid(format!(
+                "part {:?} has no Content-Type to add {name:?} to",
+                self.name
+            )));
+        };
+        if !crate::parse::is_token(name) {
+            return Err(invalid(format!("invalid parameter name {name:?}")));
+        }
+        if value.contains(|c: char| c.is_control()) {
+            return Err(invalid(format!("invalid value for parameter {name:?}")));
+        }
+        let content_type = content_type.to_mut();
+        content_type.push_str("; ");
+        content_type.push_str(name);
+        content_type.push('=');
+        if crate::parse::is_token(value) {
+            content_type.push_str(value);
+        } else {
+            content_type.push('"');
+            content_type.push_str(&value.replace('\\', "\\\\").replace('"', "\\\""));
+            content_type.push('"');
+        }
+        Ok(self)
+    }
+
+    /// Whether the part is a plain non-file field.
+    pub(crate) fn is_text(&self) -> bool {
+        self.filename.is_none()
+            && self.content_type.is_none()
+            && self.disposition == DispositionParams::default()
+            && self.body().is_some()
+    }
+
+    pub(crate) fn disposition(&self) -> &DispositionParams {
+        &self.disposition
+    }
+
+    /// The name of the field.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The `filename` parameter, if set.
+    #[must_use]
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// The part's `Content-Type`, if set. Non-file fields have none.
+    #[must_use]
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// The body of the part, unless it is read from a file.
+    #[must_use]
+    pub fn body(&self) -> Option<&[u8]> {
+        match &self.body {
+            Body::Bytes(body) => Some(body),
+            #[cfg(feature = "bytes")]
+            Body::Shared(body) => Some(body),
+            Body::Path(_) => None,
+        }
+    }
+
+    /// The path of the file the body is read from, if it is read from a file.
+    #[must_use]
+    pub fn file_path(&self) -> Option<&Path> {
+        match &self.body {
+            Body::Path(path) => Some(path),
+            Body::Bytes(_) => None,
+            #[cfg(feature = "bytes")]
+            Body::Shared(_) => None,
+        }
+    }
+
+    /// Returns `true` if the part was created with [`Part::secret`].
+    #[must_use]
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// The same part with `body` in place of the file it would be read from, for replaying a
+    /// file that was already read. `metadata` is the file's, for
+    /// [`FormData::set_file_metadata`][crate::FormData::set_file_metadata].
+    pub(crate) fn replayed<'b>(&'b self, body: &'b [u8], metadata: Option<&Metadata>) -> Part<'b> {
+        Part {
+            name: Cow::Borrowed(&self.name),
+            filename: self.filename.as_deref().map(Cow::Borrowed),
+            content_type: self.content_type.as_deref().map(Cow::Borrowed),
+            body: Body::Bytes(Cow::Borrowed(body)),
+            sensitive: self.sensitive,
+            disposition: match metadata {
+                Some(metadata) => self.disposition.or_metadata(metadata),
+                None => self.disposition,
+            },
+        }
+    }
+
+    /// Copies anything the part borrows, so that it can be kept, such as in a
+    /// [`FormDataTemplate`][crate::FormDataTemplate].
+    #[must_use]
+    pub fn into_owned(mut self) -> Part<'static> {
+        let owned = |cow: &mut Cow<'a, str>| Cow::Owned(std::mem::take(cow).into_owned());
+        Part {
+            name: owned(&mut self.name),
+            filename: self.filename.as_mut().map(owned),
+            content_type: self.content_type.as_mut().map(owned),
+            body: match &mut self.body {
+                Body::Bytes(body) => Body::Bytes(Cow::Owned(std::mem::take(body).into_owned())),
+                #[cfg(feature = "bytes")]
+                Body::Shared(body) => Body::Shared(body.clone()),
+                Body::Path(path) => Body::Path(Cow::Owned(std::mem::take(path).into_owned())),
+            },
+            sensitive: self.sensitive,
+            disposition: self.disposition,
+        }
+    }
+}
+
+/// Converts a text value to the bytes of a body, without copying it.
+fn str_bytes(value: Cow<'_, str>) -> Cow<'_, [u8]> {
+    match value {
+        Cow::Borrowed(value) => Cow::Borrowed(value.as_bytes()),
+        Cow::Owned(value) => Cow::Owned(value.into_bytes()),
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Part<'_> {
+    fn drop(&mut self) {
+        if let (true, Body::Bytes(Cow::Owned(body))) = (self.sensitive, &mut self.body) {
+            zeroize::Zeroize::zeroize(body);
+        }
+    }
+}
+
+impl fmt::Debug for Part<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Part");
+        s.field("name", &self.name);
+        if let Some(filename) = &self.filename {
+            s.field("filename", filename);
+        }
+        if let Some(content_type) = &self.content_type {
+            s.field("content_type", content_type);
+        }
+        match &self.body {
+            _ if self.sensitive => s.field("value", &format_args!("[REDACTED]")),
+            Body::Bytes(body) if self.content_type.is_none() => {
+                s.field("value", &String::from_utf8_lossy(body))
+            }
+            Body::Bytes(body) => s.field("size", &body.len()),
+            #[cfg(feature = "bytes")]
+            Body::Shared(body) => s.field("size", &body.len()),
+            Body::Path(path) => s.field("path", path),
+        };
+        if self.disposition != DispositionParams::default() {
+            s.field("disposition", &self.disposition);
+        }
+        s.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Part;
+
+    #[test]
+    fn into_owned() {
+        let name = String::from("corro");
+        let body = b"<svg/>".to_vec();
+        let part = Part::file(name.as_str(), body, "image/svg+xml").with_filename(&*name);
+        let owned: Part<'static> = part.clone().into_owned();
+        assert_eq!(owned, part);
+    }
+
+    #[test]
+    fn with_content_type_param() {
+        let part = Part::file("data", &b""[..], "text/plain")
+            .with_content_type_param("charset", "utf-8")
+            .unwrap()
+            .with_content_type_param("x-note", r#"say "hi" \ wave"#)
+            .unwrap();
+        assert_eq!(
+            part.content_type(),
+            Some(r#"text/plain; charset=utf-8; x-note="say \"hi\" \\ wave""#)
+        );
+        assert!(Part::text("cute", "yes")
+            .with_content_type_param("charset", "utf-8")
+            .is_err());
+        let part = Part::file("data", &b""[..], "text/plain");
+        assert!(part
+            .clone()
+            .with_content_type_param("bad name", "x")
+            .is_err());
+        assert!(part.with_content_type_param("x", "line\r\nbreak").is_err());
+    }
+}
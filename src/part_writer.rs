@@ -0,0 +1,170 @@
+use crate::{check_interrupted, FormData};
+use std::fmt;
+use std::io::{Error, Result, Write};
+
+/// A part whose body is being written, returned by
+/// [`FormData::start_part`][crate::FormData::start_part].
+///
+/// Everything written to the `PartWriter` becomes the part's body. The part ends when
+/// [`PartWriter::end`] is called. A `PartWriter` that is dropped without calling `end`, or whose
+/// body failed to be written, leaves the part unfinished like any other failed part: it isn't
+/// counted in [`FormData::last_progress`], and the document should be resumed or aborted.
+///
+/// Text can be written with `write!` and `writeln!`, whether [`std::io::Write`] or
+/// [`std::fmt::Write`] is in scope:
+///
+/// ```
+/// # use form_data_builder::FormData;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut form = FormData::new(Vec::new());
+/// let mut part = form.start_part("squares", Some("squares.csv".as_ref()), Some("text/csv"))?;
+/// writeln!(part, "n,square")?;
+/// for n in 1..=10 {
+///     writeln!(part, "{n},{}", n * n)?;
+/// }
+/// part.end()?;
+/// # form.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PartWriter<'a, W: Write> {
+    form: &'a mut FormData<W>,
+    size: u64,
+    /// Whether writing the body failed, in which case the part can't be ended.
+    failed: bool,
+}
+
+impl<'a, W: Write> PartWriter<'a, W> {
+    pub(crate) fn new(form: &'a mut FormData<W>) -> PartWriter<'a, W> {
+        PartWriter {
+            form,
+            size: 0,
+            failed: false,
+        }
+    }
+
+    /// Writes formatted text to the body. This is what `write!` and `writeln!` call, and unlike
+    /// the [`std::fmt::Write`] impl, it keeps the underlying I/O error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer fails.
+    pub fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+        Write::write_fmt(self, args)
+    }
+
+    /// Ends the part.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the body failed earlier, in which case the part is left
+    /// unfinished, or if the writer fails.
+    pub fn end(self) -> Result<()> {
+        if self.failed {
+            return Err(self
+                .form
+                .part_error(Error::other("writing the part's body failed earlier")));
+        }
+        self.form.end_part(self.size)
+    }
+}
+
+impl<W: Write> Write for PartWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = check_interrupted(
+            self.form.options.cancellation.as_deref(),
+            self.form.deadline(),
+        )
+        .and_then(|()| {
+            self.form
+                .writer
+                .as_mut()
+                .expect("a part header was just written")
+                .write(buf)
+        })
+        .map_err(|err| {
+            self.failed = true;
+            self.form.part_error(err)
+        })?;
+        #[cfg(feature = "manifest")]
+        if let Some(sha256) = &mut self.form.sha256 {
+            sha2::Digest::update(sha256, &buf[..n]);
+        }
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.form
+            .writer
+            .as_mut()
+            .expect("a part header was just written")
+            .flush()
+            .map_err(|err| {
+                self.failed = true;
+                self.form.part_error(err)
+            })
+    }
+}
+
+impl<W: Write> fmt::Write for PartWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FormData;
+
+    #[test]
+    fn write_text() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        let mut part = form.start_part("cute", None, None).unwrap();
+        let e = 'e';
+        write!(part, "y{e}").unwrap();
+        std::fmt::Write::write_str(&mut part, "s").unwrap();
+        part.end().unwrap();
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+    }
+
+    #[test]
+    fn failed() {
+        use std::io::Write;
+
+        let mut form = FormData::with_boundary(Limited(300), "boundary").unwrap();
+        form.write_field("cute", "yes").unwrap();
+        let progress = form.last_progress();
+        assert_eq!(progress.parts, 1);
+
+        let mut part = form.start_part("long", None, None).unwrap();
+        assert!(part.write_all(&[b'x'; 300]).is_err());
+        assert!(part.end().is_err());
+        assert_eq!(form.last_progress(), progress);
+
+        // dropping without ending leaves the part unfinished too
+        form.start_part("dropped", None, None).unwrap();
+        assert_eq!(form.last_progress(), progress);
+        form.abort();
+    }
+
+    /// A writer that fails once it has been given more than this many bytes.
+    struct Limited(usize);
+
+    impl std::io::Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.len() > self.0 {
+                return Err(std::io::Error::other("full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
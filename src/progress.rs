@@ -0,0 +1,16 @@
+/// How much of a document was written intact. See
+/// [`FormData::last_progress`][crate::FormData::last_progress].
+///
+/// Progress is counted in whole parts: a part that failed partway through isn't included, so
+/// everything up to [`Progress::bytes`] is a valid prefix of the document, and writing can pick
+/// up again from the next part.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Progress {
+    /// The number of parts written in full. Parts left out by
+    /// [`FileTypePolicy::Skip`][crate::FileTypePolicy::Skip] are counted too, so that this
+    /// matches the number of parts that were asked for.
+    pub parts: usize,
+    /// The number of bytes written up to the end of the last complete part.
+    pub bytes: u64,
+}
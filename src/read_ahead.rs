@@ -0,0 +1,94 @@
+use std::io::{ErrorKind, Read, Result};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread;
+
+/// How much a background thread reads at a time for [`FormData::set_read_ahead`].
+///
+/// [`FormData::set_read_ahead`]: crate::FormData::set_read_ahead
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A reader that reads the next chunk from `R` on a background thread while the caller is busy
+/// with the current one.
+///
+/// Buffers go back to the thread once they have been read out, so at most three are ever
+/// allocated: one being read into, one waiting, and one being read out of.
+pub(crate) struct ReadAhead {
+    full: Receiver<Result<Vec<u8>>>,
+    empty: Sender<Vec<u8>>,
+    current: Vec<u8>,
+    pos: usize,
+}
+
+impl ReadAhead {
+    pub(crate) fn new<R: Read + Send + 'static>(reader: R) -> ReadAhead {
+        let (full_tx, full) = mpsc::sync_channel(1);
+        let (empty, empty_rx) = mpsc::channel();
+        thread::spawn(move || fill(reader, &full_tx, &empty_rx));
+        ReadAhead {
+            full,
+            empty,
+            current: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+/// Runs on the background thread until the reader is exhausted or fails, or the [`ReadAhead`]
+/// is dropped.
+fn fill<R: Read>(mut reader: R, full: &SyncSender<Result<Vec<u8>>>, empty: &Receiver<Vec<u8>>) {
+    loop {
+        let mut buf = empty.try_recv().unwrap_or_default();
+        buf.resize(CHUNK_SIZE, 0);
+        let n = loop {
+            match reader.read(&mut buf) {
+                Ok(n) => break n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => {
+                    let _ = full.send(Err(err));
+                    return;
+                }
+            }
+        };
+        if n == 0 {
+            return;
+        }
+        buf.truncate(n);
+        if full.send(Ok(buf)).is_err() {
+            return;
+        }
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.pos == self.current.len() {
+            let next = match self.full.recv() {
+                Ok(next) => next?,
+                // the thread hung up at the end of the reader
+                Err(_) => return Ok(0),
+            };
+            let _ = self.empty.send(std::mem::replace(&mut self.current, next));
+            self.pos = 0;
+        }
+        let n = buf.len().min(self.current.len() - self.pos);
+        buf[..n].copy_from_slice(&self.current[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReadAhead, CHUNK_SIZE};
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn chunks() {
+        let data: Vec<u8> = (0..=u8::MAX).cycle().take(CHUNK_SIZE * 3 + 17).collect();
+        let mut read = Vec::new();
+        ReadAhead::new(Cursor::new(data.clone()))
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, data);
+    }
+}
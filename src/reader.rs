@@ -0,0 +1,395 @@
+use crate::{FormData, Part};
+use std::io::{Error, ErrorKind, Read, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::Waker;
+use std::vec;
+
+/// A document that is written as it is read, created with
+/// [`FormDataBuilder::into_reader`][crate::FormDataBuilder::into_reader].
+///
+/// Each part is written to memory when the reader reaches it, so only one part is held at a
+/// time rather than the whole document.
+///
+/// Reading can be paused from elsewhere, such as by a congestion-control layer, with the
+/// [`PauseHandle`] from [`FormDataReader::pause_handle`]. While paused, reads fail with an error
+/// of kind [`ErrorKind::WouldBlock`] and write nothing, and the reader picks up where it left off
+/// once resumed:
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{FormData, Part};
+/// use std::io::{ErrorKind, Read};
+///
+/// let mut reader = FormData::builder()
+///     .part(Part::text("cute", "yes"))
+///     .into_reader()?;
+/// let pause = reader.pause_handle();
+/// pause.pause();
+/// let err = reader.read(&mut [0; 64]).unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::WouldBlock);
+/// pause.resume();
+/// reader.read_to_end(&mut Vec::new())?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FormDataReader<'a> {
+    form: FormData<Vec<u8>>,
+    parts: vec::IntoIter<Part<'a>>,
+    /// The bytes written for the latest part, of which `pos` have been read.
+    chunk: Vec<u8>,
+    pos: usize,
+    pause: PauseHandle,
+}
+
+impl<'a> FormDataReader<'a> {
+    pub(crate) fn new(form: FormData<Vec<u8>>, parts: Vec<Part<'a>>) -> FormDataReader<'a> {
+        FormDataReader {
+            form,
+            parts: parts.into_iter(),
+            chunk: Vec::new(),
+            pos: 0,
+            pause: PauseHandle::default(),
+        }
+    }
+
+    /// Returns the boundary that separates the parts of the document.
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        self.form.boundary()
+    }
+
+    /// Returns the value of the `Content-Type` header for the document, like
+    /// [`FormData::content_type_header`].
+    #[must_use]
+    pub fn content_type_header(&self) -> String {
+        self.form.content_type_header()
+    }
+
+    /// Returns a handle for pausing and resuming the reader.
+    #[must_use]
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.pause.clone()
+    }
+
+    /// Writes the next part, or the closing delimiter after the last one, into `chunk`. Returns
+    /// `false` at the end of the document.
+    fn fill(&mut self) -> Result<bool> {
+        self.pos = 0;
+        self.chunk.clear();
+        let Some(writer) = self.form.writer.as_mut() else {
+            return if self.form.finished {
+                Ok(false)
+            } else {
+                Err(Error::other("writing the document failed earlier"))
+            };
+        };
+        let Some(part) = self.parts.next() else {
+            self.chunk = self.form.finish()?;
+            return Ok(true);
+        };
+        writer.clear();
+        let result = self.form.write_part(&part);
+        if let Some(writer) = self.form.writer.as_mut() {
+            std::mem::swap(&mut self.chunk, writer);
+        }
+        if let Err(err) = result {
+            self.chunk.clear();
+            self.form.abort();
+            return Err(err);
+        }
+        Ok(true)
+    }
+}
+
+impl Read for FormDataReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.pause.is_paused() {
+            return Err(Error::new(ErrorKind::WouldBlock, "the document is paused"));
+        }
+        while self.pos == self.chunk.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// A document dropped partway through was abandoned on purpose, so it shouldn't trip the
+/// `leak-check` feature.
+impl Drop for FormDataReader<'_> {
+    fn drop(&mut self) {
+        self.form.abort();
+    }
+}
+
+/// Pauses and resumes a [`FormDataReader`] or `FormDataStream` from elsewhere. Clones share the
+/// same state, like the flag passed to [`FormData::set_cancellation`].
+///
+/// Pausing takes effect at the next read or poll; a part that is already being written is
+/// finished first.
+#[derive(Debug, Clone, Default)]
+pub struct PauseHandle(Arc<PauseState>);
+
+#[derive(Debug, Default)]
+struct PauseState {
+    paused: AtomicBool,
+    /// The task waiting on a paused stream, woken by [`PauseHandle::resume`].
+    waker: Mutex<Option<Waker>>,
+}
+
+impl PauseHandle {
+    /// Stops the document from being produced until [`PauseHandle::resume`] is called.
+    pub fn pause(&self) {
+        self.0.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Lets the document be produced again, waking a stream that is waiting on it.
+    pub fn resume(&self) {
+        self.0.paused.store(false, Ordering::Relaxed);
+        let waker = self
+            .0
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Returns whether the document is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the document is paused, and if so, keeps `waker` to wake on resume.
+    #[cfg_attr(not(feature = "stream"), allow(dead_code))]
+    pub(crate) fn poll_paused(&self, waker: &Waker) -> bool {
+        // checked under the lock, so that a `resume` racing with this either is seen here or
+        // finds the waker
+        let mut slot = self.0.waker.lock().unwrap_or_else(PoisonError::into_inner);
+        let paused = self.is_paused();
+        if paused {
+            *slot = Some(waker.clone());
+        }
+        paused
+    }
+}
+
+/// A document that is written as it is polled, as a [`Stream`] of [`Bytes`] with one chunk per
+/// part. Created with [`FormDataBuilder::into_stream`][crate::FormDataBuilder::into_stream].
+///
+/// Like [`FormDataReader`], which it wraps, each part is written when the stream reaches it.
+/// Parts read from files are read on the task polling the stream. While the stream's
+/// [`PauseHandle`] is paused, polling it returns [`Poll::Pending`] until it is resumed. The stream
+/// ends after the first error.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{FormData, Part};
+///
+/// let stream = FormData::builder()
+///     .part(Part::path("corro", "testdata/corro.svg", "image/svg+xml"))
+///     .into_stream()?;
+/// let pause = stream.pause_handle();
+/// // ... hand `pause` to whatever watches for congestion, then send `stream` ...
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Bytes`]: bytes::Bytes
+/// [`Poll::Pending`]: std::task::Poll::Pending
+/// [`Stream`]: futures_util::Stream
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct FormDataStream<'a> {
+    reader: FormDataReader<'a>,
+}
+
+#[cfg(feature = "stream")]
+impl<'a> FormDataStream<'a> {
+    pub(crate) fn new(reader: FormDataReader<'a>) -> FormDataStream<'a> {
+        FormDataStream { reader }
+    }
+
+    /// Returns the boundary that separates the parts of the document.
+    #[must_use]
+    pub fn boundary(&self) -> &str {
+        self.reader.boundary()
+    }
+
+    /// Returns the value of the `Content-Type` header for the document, like
+    /// [`FormData::content_type_header`].
+    #[must_use]
+    pub fn content_type_header(&self) -> String {
+        self.reader.content_type_header()
+    }
+
+    /// Returns a handle for pausing and resuming the stream.
+    #[must_use]
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.reader.pause_handle()
+    }
+}
+
+#[cfg(feature = "stream")]
+impl futures_util::Stream for FormDataStream<'_> {
+    type Item = Result<bytes::Bytes>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<bytes::Bytes>>> {
+        use std::task::Poll;
+
+        let reader = &mut self.get_mut().reader;
+        if reader.form.writer.is_none() {
+            return Poll::Ready(None);
+        }
+        if reader.pause.poll_paused(cx.waker()) {
+            return Poll::Pending;
+        }
+        Poll::Ready(match reader.fill() {
+            Ok(true) => Some(Ok(std::mem::take(&mut reader.chunk).into())),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FormData, FormDataOptions, Part};
+    use std::io::{ErrorKind, Read};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn parts() -> [Part<'static>; 3] {
+        [
+            Part::text("cute", "yes"),
+            Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+            Part::file("empty", &b""[..], "text/plain"),
+        ]
+    }
+
+    #[test]
+    fn matches_form_data() {
+        let mut reader = FormData::builder()
+            .boundary("boundary")
+            .parts(parts())
+            .into_reader()
+            .unwrap();
+        let mut document = Vec::new();
+        let mut buf = [0; 7];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                len => document.extend_from_slice(&buf[..len]),
+            }
+        }
+        let expected = FormData::builder()
+            .boundary("boundary")
+            .parts(parts())
+            .emit(Vec::new())
+            .unwrap();
+        assert_eq!(document, expected);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn pause() {
+        let mut reader = FormData::builder()
+            .boundary("boundary")
+            .parts(parts())
+            .into_reader()
+            .unwrap();
+        let mut document = vec![0; 10];
+        reader.read_exact(&mut document).unwrap();
+        let pause = reader.pause_handle();
+        pause.pause();
+        assert!(pause.is_paused());
+        let err = reader.read(&mut [0; 10]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+        pause.resume();
+        reader.read_to_end(&mut document).unwrap();
+        let expected = FormData::builder()
+            .boundary("boundary")
+            .parts(parts())
+            .emit(Vec::new())
+            .unwrap();
+        assert_eq!(document, expected);
+    }
+
+    #[test]
+    fn error() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let options = FormDataOptions {
+            cancellation: Some(cancelled.clone()),
+            ..FormDataOptions::default()
+        };
+        let mut reader = FormData::builder()
+            .options(options)
+            .parts(parts())
+            .into_reader()
+            .unwrap();
+        reader.read_exact(&mut [0; 10]).unwrap();
+        cancelled.store(true, Ordering::Relaxed);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap_err();
+        // the rest of the first part was read, but the document doesn't look finished
+        assert!(!rest.ends_with(b"--\r\n"));
+        reader.read_to_end(&mut rest).unwrap_err();
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream() {
+        use futures_util::Stream;
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        struct Flag(AtomicBool);
+
+        impl Wake for Flag {
+            fn wake(self: Arc<Flag>) {
+                self.0.store(true, Ordering::Relaxed);
+            }
+        }
+
+        let mut stream = FormData::builder()
+            .boundary("boundary")
+            .parts(parts())
+            .into_stream()
+            .unwrap();
+        let woken = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        let pause = stream.pause_handle();
+        pause.pause();
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        pause.resume();
+        assert!(woken.0.load(Ordering::Relaxed));
+
+        let mut document = Vec::new();
+        let mut chunks = 0;
+        while let Poll::Ready(Some(chunk)) = Pin::new(&mut stream).poll_next(&mut cx) {
+            document.extend_from_slice(&chunk.unwrap());
+            chunks += 1;
+        }
+        // one per part, then the closing delimiter
+        assert_eq!(chunks, 4);
+        let expected = FormData::builder()
+            .boundary("boundary")
+            .parts(parts())
+            .emit(Vec::new())
+            .unwrap();
+        assert_eq!(document, expected);
+    }
+}
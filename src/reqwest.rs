@@ -0,0 +1,88 @@
+use crate::{FormDataTemplate, Part};
+use std::io::{Error, ErrorKind, Result};
+
+/// Converts a part, reading its file if it has one. This is for gradually moving code between
+/// this crate and reqwest's own multipart support.
+///
+/// reqwest writes the part's headers itself, so parameters like
+/// [`Part::with_modification_date`] are left out.
+impl TryFrom<&Part<'_>> for reqwest::multipart::Part {
+    type Error = Error;
+
+    fn try_from(part: &Part<'_>) -> Result<Self> {
+        let body = match part.file_path() {
+            Some(path) => std::fs::read(path)?,
+            None => part.body().unwrap_or_default().to_vec(),
+        };
+        let mut converted = reqwest::multipart::Part::bytes(body);
+        if let Some(filename) = part.filename() {
+            converted = converted.file_name(filename.to_owned());
+        }
+        if let Some(content_type) = part.content_type() {
+            converted = converted
+                .mime_str(content_type)
+                .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        }
+        Ok(converted)
+    }
+}
+
+/// Converts every part of a template, in order, reading their files. See the conversion for
+/// [`Part`]s.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{FormDataTemplate, Part};
+///
+/// let mut template = FormDataTemplate::new();
+/// template.push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+/// template.push(Part::text("cute", "yes"));
+/// let form = reqwest::multipart::Form::try_from(&template)?;
+/// // ... send it with `reqwest::RequestBuilder::multipart` ...
+/// # Ok(())
+/// # }
+/// ```
+impl TryFrom<&FormDataTemplate<'_>> for reqwest::multipart::Form {
+    type Error = Error;
+
+    fn try_from(template: &FormDataTemplate<'_>) -> Result<Self> {
+        template
+            .parts()
+            .iter()
+            .try_fold(reqwest::multipart::Form::new(), |form, part| {
+                Ok(form.part(part.name().to_owned(), part.try_into()?))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FormDataTemplate, Part};
+    use futures_util::TryStreamExt;
+
+    #[tokio::test]
+    async fn form() {
+        let mut template = FormDataTemplate::new();
+        template
+            .push(Part::path("corro", "testdata/corro.svg", "image/svg+xml").with_filename("c.svg"))
+            .push(Part::text("cute", "yes"));
+        let form = reqwest::multipart::Form::try_from(&template).unwrap();
+        let boundary = form.boundary().to_owned();
+        let document: Vec<u8> = form
+            .into_stream()
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await
+            .unwrap();
+
+        let parts = crate::parse::FormDataParts::parse(&document, &boundary).unwrap();
+        let corro = parts.get("corro").unwrap();
+        assert_eq!(corro.filename(), Some("c.svg"));
+        assert_eq!(corro.content_type(), Some("image/svg+xml"));
+        assert_eq!(corro.body(), std::fs::read("testdata/corro.svg").unwrap());
+        assert_eq!(parts.get("cute").unwrap().body(), b"yes");
+
+        let bad = [Part::file("bad", &b""[..], "not a content type")];
+        assert!(reqwest::multipart::Part::try_from(&bad[0]).is_err());
+    }
+}
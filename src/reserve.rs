@@ -0,0 +1,40 @@
+use std::io::Cursor;
+
+/// An in-memory writer that can make room for more bytes up front, for
+/// [`FormData::reserve`][crate::FormData::reserve].
+pub trait Reserve {
+    /// Makes room for at least `additional` more bytes.
+    fn reserve(&mut self, additional: usize);
+}
+
+impl Reserve for Vec<u8> {
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+}
+
+impl Reserve for &mut Vec<u8> {
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+}
+
+impl Reserve for Cursor<Vec<u8>> {
+    fn reserve(&mut self, additional: usize) {
+        self.get_mut().reserve(additional);
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Reserve for bytes::BytesMut {
+    fn reserve(&mut self, additional: usize) {
+        bytes::BytesMut::reserve(self, additional);
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Reserve for bytes::buf::Writer<bytes::BytesMut> {
+    fn reserve(&mut self, additional: usize) {
+        self.get_mut().reserve(additional);
+    }
+}
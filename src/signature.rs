@@ -0,0 +1,75 @@
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use std::fmt;
+use std::io::{Result, Write};
+
+/// A writer that computes an HMAC-SHA256 of everything written to it, for
+/// [`FormData::sign_hmac`][crate::FormData::sign_hmac].
+#[derive(Clone)]
+pub struct HmacWriter {
+    mac: Hmac<Sha256>,
+    field: String,
+}
+
+impl HmacWriter {
+    pub(crate) fn new(key: &[u8], field: &str) -> HmacWriter {
+        HmacWriter {
+            mac: Hmac::new_from_slice(key).expect("HMAC takes keys of any length"),
+            field: field.to_owned(),
+        }
+    }
+
+    /// The name of the field the signature is written to.
+    pub(crate) fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The HMAC of everything written so far, in lowercase hex.
+    pub(crate) fn hex(&self) -> String {
+        use std::fmt::Write;
+
+        let mut hex = String::with_capacity(64);
+        for byte in self.mac.clone().finalize().into_bytes() {
+            let _ = write!(hex, "{byte:02x}");
+        }
+        hex
+    }
+}
+
+/// Leaves out the key-dependent state.
+impl fmt::Debug for HmacWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacWriter")
+            .field("field", &self.field)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Write for HmacWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.mac.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HmacWriter;
+    use std::io::Write;
+
+    #[test]
+    fn hex() {
+        // RFC 4231 test case 2
+        let mut writer = HmacWriter::new(b"Jefe", "signature");
+        writer.write_all(b"what do ya want ").unwrap();
+        writer.write_all(b"for nothing?").unwrap();
+        assert_eq!(
+            writer.hex(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::path::PathBuf;
+
+/// Something that produces the body of a part, written with
+/// [`FormData::write_source`][crate::FormData::write_source].
+///
+/// A source can also suggest a `filename` parameter and `Content-Type`. A source with neither is
+/// written as a non-file field. A source with a filename but no content type gets one from the
+/// filename's extension, as with [`FormData::write_path_auto`][crate::FormData::write_path_auto].
+///
+/// Closures returning a reader are sources, for bodies that are expensive to produce:
+///
+/// ```
+/// # use form_data_builder::FormData;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut form = FormData::new(Vec::new());
+/// form.write_source("corro", || std::fs::File::open("testdata/corro.svg"))?;
+/// # form.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Byte slices, strings, [`File`]s and [`PathBuf`]s are sources too, as is `bytes::Bytes` with the
+/// `bytes` feature. In-memory bodies are written without being copied first, and a `PathBuf` is
+/// opened when it is written, with the filename taken from the path. Any other reader can be
+/// wrapped in a [`ReaderSource`].
+pub trait PartSource {
+    /// The reader returned by [`PartSource::reader`].
+    type Reader<'a>: Read
+    where
+        Self: 'a;
+
+    /// Returns a reader for the body. This is called once each time the source is written, before
+    /// the part's headers are.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body can't be read, which stops the part from being written.
+    fn reader(&mut self) -> Result<Self::Reader<'_>>;
+
+    /// The length of the body in bytes, if it is known ahead of time. This is what
+    /// [`FormData::content_length`][crate::FormData::content_length] adds up, so it must be exact.
+    fn len_hint(&self) -> Option<u64> {
+        None
+    }
+
+    /// The `filename` parameter to send, if any.
+    fn filename(&self) -> Option<&OsStr> {
+        None
+    }
+
+    /// The `Content-Type` to send, if any.
+    fn content_type(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl<F, R> PartSource for F
+where
+    F: FnMut() -> Result<R>,
+    R: Read,
+{
+    type Reader<'a>
+        = R
+    where
+        F: 'a;
+
+    fn reader(&mut self) -> Result<R> {
+        self()
+    }
+}
+
+/// Implements [`PartSource`] for types that can be borrowed as a byte slice.
+macro_rules! slice_source {
+    ($($ty:ty),*) => {$(
+        impl PartSource for $ty {
+            type Reader<'a>
+                = &'a [u8]
+            where
+                Self: 'a;
+
+            fn reader(&mut self) -> Result<&[u8]> {
+                Ok(AsRef::<[u8]>::as_ref(&*self))
+            }
+
+            fn len_hint(&self) -> Option<u64> {
+                Some(AsRef::<[u8]>::as_ref(self).len() as u64)
+            }
+        }
+    )*};
+}
+
+slice_source!(&[u8], Vec<u8>, &str, String, Cow<'_, [u8]>);
+#[cfg(feature = "bytes")]
+slice_source!(bytes::Bytes);
+
+/// Reads from the file's current position.
+impl PartSource for File {
+    type Reader<'a> = &'a File;
+
+    fn reader(&mut self) -> Result<&File> {
+        Ok(self)
+    }
+
+    fn len_hint(&self) -> Option<u64> {
+        let len = self.metadata().ok()?.len();
+        let position = (&*self).stream_position().ok()?;
+        Some(len.saturating_sub(position))
+    }
+}
+
+impl PartSource for PathBuf {
+    type Reader<'a> = File;
+
+    fn reader(&mut self) -> Result<File> {
+        File::open(self)
+    }
+
+    fn len_hint(&self) -> Option<u64> {
+        Some(std::fs::metadata(self).ok()?.len())
+    }
+
+    fn filename(&self) -> Option<&OsStr> {
+        self.file_name()
+    }
+}
+
+/// A [`PartSource`] that reads the body from any reader, with the hints given to it.
+///
+/// ```
+/// # use form_data_builder::{FormData, ReaderSource};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let mut form = FormData::new(Vec::new());
+/// let source = ReaderSource::new(std::io::stdin())
+///     .with_filename("input.txt")
+///     .with_content_type("text/plain");
+/// # let source = ReaderSource::new(std::io::empty());
+/// form.write_source("input", source)?;
+/// # form.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReaderSource<R> {
+    reader: Option<R>,
+    len: Option<u64>,
+    filename: Option<OsString>,
+    content_type: Option<String>,
+}
+
+impl<R> ReaderSource<R> {
+    /// Wraps `reader`, with no hints. It can only be written once.
+    ///
+    /// With the `tokio` feature, `reader` can also be a [`tokio::io::AsyncRead`], making this an
+    /// [`AsyncPartSource`][crate::AsyncPartSource].
+    pub fn new(reader: R) -> ReaderSource<R> {
+        ReaderSource {
+            reader: Some(reader),
+            len: None,
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    /// Sets the length of the body, which must be the number of bytes `reader` produces.
+    #[must_use]
+    pub fn with_len(mut self, len: u64) -> ReaderSource<R> {
+        self.len = Some(len);
+        self
+    }
+
+    /// Sets the `filename` parameter.
+    #[must_use]
+    pub fn with_filename<F: Into<OsString>>(mut self, filename: F) -> ReaderSource<R> {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Sets the `Content-Type`.
+    #[must_use]
+    pub fn with_content_type<C: Into<String>>(mut self, content_type: C) -> ReaderSource<R> {
+        self.content_type = Some(content_type.into());
+        self
+    }
+}
+
+impl<R: Seek> ReaderSource<R> {
+    /// Sets the length of the body to what remains of `reader` from its current position, found
+    /// by seeking to the end and back. This lets
+    /// [`FormData::content_length`][crate::FormData::content_length] include readers such as
+    /// [`std::io::Cursor`]s and [`File`]s.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, ReaderSource};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = std::fs::File::open("testdata/corro.svg")?;
+    /// let source = ReaderSource::new(file).with_filename("corro.svg").with_measured_len()?;
+    ///
+    /// let mut form = FormData::new(Vec::new());
+    /// let content_length = form.content_length([("corro", &source)]);
+    /// form.write_source("corro", source)?;
+    /// assert_eq!(content_length, Some(form.finish()?.len() as u64));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if seeking fails.
+    pub fn with_measured_len(mut self) -> Result<ReaderSource<R>> {
+        if let Some(reader) = &mut self.reader {
+            let position = reader.stream_position()?;
+            let end = reader.seek(SeekFrom::End(0))?;
+            if end != position {
+                reader.seek(SeekFrom::Start(position))?;
+            }
+            self.len = Some(end.saturating_sub(position));
+        }
+        Ok(self)
+    }
+}
+
+impl<R> ReaderSource<R> {
+    fn take_reader(&mut self) -> Result<R> {
+        self.reader
+            .take()
+            .ok_or_else(|| std::io::Error::other("a `ReaderSource` can only be written once"))
+    }
+}
+
+impl<R: Read> PartSource for ReaderSource<R> {
+    type Reader<'a>
+        = R
+    where
+        R: 'a;
+
+    fn reader(&mut self) -> Result<R> {
+        self.take_reader()
+    }
+
+    fn len_hint(&self) -> Option<u64> {
+        self.len
+    }
+
+    fn filename(&self) -> Option<&OsStr> {
+        self.filename.as_deref()
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+/// The asynchronous counterpart of [`PartSource`], for bodies read from a
+/// [`tokio::io::AsyncRead`] such as an object store download. Written with
+/// [`FormData::write_source_async`][crate::FormData::write_source_async].
+///
+/// ```
+/// use form_data_builder::{AsyncPartSource, FormData};
+/// use std::ffi::OsStr;
+///
+/// /// An object in a bucket, fetched when it is written.
+/// struct Object {
+///     key: String,
+/// }
+///
+/// impl AsyncPartSource for Object {
+///     type Reader<'a> = &'static [u8];
+///
+///     async fn reader(&mut self) -> std::io::Result<&'static [u8]> {
+///         // a real implementation would start a download here
+///         Ok(b"<svg/>")
+///     }
+///
+///     async fn len_hint(&self) -> Option<u64> {
+///         // ... and ask for the object's size here
+///         Some(6)
+///     }
+///
+///     fn filename(&self) -> Option<&OsStr> {
+///         Some(self.key.as_ref())
+///     }
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut form = FormData::new(Vec::new());
+/// let object = Object { key: "corro.svg".to_owned() };
+/// form.write_source_async("corro", object).await?;
+/// # form.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub trait AsyncPartSource {
+    /// The reader returned by [`AsyncPartSource::reader`].
+    type Reader<'a>: tokio::io::AsyncRead + Unpin
+    where
+        Self: 'a;
+
+    /// Returns a reader for the body. This is called once each time the source is written, before
+    /// the part's headers are.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body can't be read, which stops the part from being written.
+    fn reader(&mut self) -> impl std::future::Future<Output = Result<Self::Reader<'_>>>;
+
+    /// The length of the body in bytes, if it can be found out ahead of time.
+    fn len_hint(&self) -> impl std::future::Future<Output = Option<u64>> {
+        std::future::ready(None)
+    }
+
+    /// The `filename` parameter to send, if any.
+    fn filename(&self) -> Option<&OsStr> {
+        None
+    }
+
+    /// The `Content-Type` to send, if any.
+    fn content_type(&self) -> Option<&str> {
+        None
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncRead + Unpin> AsyncPartSource for ReaderSource<R> {
+    type Reader<'a>
+        = R
+    where
+        R: 'a;
+
+    async fn reader(&mut self) -> Result<R> {
+        self.take_reader()
+    }
+
+    async fn len_hint(&self) -> Option<u64> {
+        self.len
+    }
+
+    fn filename(&self) -> Option<&OsStr> {
+        self.filename.as_deref()
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PartSource, ReaderSource};
+    use crate::FormData;
+    use std::borrow::Cow;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    #[test]
+    fn write_source() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_source("cute", || Ok(Cursor::new("yes")))
+            .unwrap();
+        let source = ReaderSource::new(Cursor::new("<svg/>")).with_filename("corro.svg");
+        form.write_source("corro", source).unwrap();
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        expected
+            .write_file(
+                "corro",
+                Cursor::new("<svg/>"),
+                Some("corro.svg".as_ref()),
+                "image/svg+xml",
+            )
+            .unwrap();
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+    }
+
+    #[test]
+    fn impls() {
+        fn len_hint<S: PartSource>(source: &S) -> Option<u64> {
+            source.len_hint()
+        }
+
+        let corro = std::fs::read("testdata/corro.svg").unwrap();
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.write_source("a", &b"yes"[..]).unwrap();
+        form.write_source("b", b"yes".to_vec()).unwrap();
+        form.write_source("c", "yes").unwrap();
+        form.write_source("d", "yes".to_owned()).unwrap();
+        form.write_source("e", Cow::Borrowed(&b"yes"[..])).unwrap();
+        let file = std::fs::File::open("testdata/corro.svg").unwrap();
+        assert_eq!(len_hint(&file), Some(corro.len() as u64));
+        form.write_source("f", file).unwrap();
+        let path = PathBuf::from("testdata/corro.svg");
+        assert_eq!(len_hint(&path), Some(corro.len() as u64));
+        form.write_source("g", path).unwrap();
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        for name in ["a", "b", "c", "d", "e"] {
+            expected.write_field(name, "yes").unwrap();
+        }
+        expected
+            .write_source("f", || Ok(Cursor::new(&corro)))
+            .unwrap();
+        expected
+            .write_path("g", "testdata/corro.svg", "image/svg+xml")
+            .unwrap();
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+        assert_eq!(len_hint(&"yes"), Some(3));
+    }
+
+    #[test]
+    fn content_length() {
+        let mut form = FormData::new(Vec::new());
+        form.write_field("cute", "yes").unwrap();
+        let unknown = ReaderSource::new(Cursor::new("<svg/>")).with_filename("corro.svg");
+        assert_eq!(form.content_length([("corro", &unknown)]), None);
+        let known = unknown.with_len(6);
+        let content_length = form.content_length([("corro", &known)]);
+        form.write_source("corro", known).unwrap();
+        assert_eq!(content_length, Some(form.finish().unwrap().len() as u64));
+        assert_eq!(form.content_length::<&str, _>([]), None);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn write_source_async() {
+        let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        form.set_rate_limit(std::num::NonZeroU64::new(1 << 20).unwrap());
+        let source = ReaderSource::new(&b"<svg/>"[..]).with_filename("corro.svg");
+        form.write_source_async("corro", source).await.unwrap();
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected
+            .write_source(
+                "corro",
+                ReaderSource::new(&b"<svg/>"[..]).with_filename("corro.svg"),
+            )
+            .unwrap();
+        assert_eq!(form.finish().unwrap(), expected.finish().unwrap());
+    }
+
+    #[test]
+    fn with_measured_len() {
+        let mut cursor = Cursor::new("hello, world");
+        cursor.set_position(7);
+        let mut source = ReaderSource::new(cursor).with_measured_len().unwrap();
+        assert_eq!(source.len_hint(), Some(5));
+        let mut body = String::new();
+        std::io::Read::read_to_string(&mut source.reader().unwrap(), &mut body).unwrap();
+        assert_eq!(body, "world");
+    }
+
+    #[test]
+    fn reader_source_once() {
+        let mut source = ReaderSource::new(Cursor::new("yes"));
+        assert!(source.reader().is_ok());
+        assert!(source.reader().is_err());
+    }
+}
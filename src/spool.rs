@@ -0,0 +1,253 @@
+use crate::PartSource;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom, Write};
+
+/// A writer that keeps the document in memory until it grows past a threshold, then moves it to
+/// an anonymous temporary file.
+///
+/// This suits forms that are usually small but occasionally huge: small documents never touch
+/// the disk, and huge ones never have to fit in memory.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{FormData, Spooled, SpooledWriter};
+///
+/// let mut form = FormData::new(SpooledWriter::new(1024 * 1024));
+/// form.write_path("corro", "testdata/corro.svg", "image/svg+xml")?;
+/// match form.finish()?.into_inner()? {
+///     Spooled::Memory(document) => { /* ... send `document` ... */ }
+///     Spooled::File(file) => { /* ... stream `file` ... */ }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SpooledWriter {
+    threshold: usize,
+    inner: Spooled,
+}
+
+/// A document written with a [`SpooledWriter`].
+#[derive(Debug)]
+pub enum Spooled {
+    /// The document stayed under the threshold.
+    Memory(Vec<u8>),
+    /// The document grew past the threshold. The file is positioned at its start and is deleted
+    /// when closed.
+    File(File),
+}
+
+impl SpooledWriter {
+    /// Creates a writer that moves to a temporary file once more than `threshold` bytes are
+    /// written.
+    #[must_use]
+    pub fn new(threshold: usize) -> SpooledWriter {
+        SpooledWriter {
+            threshold,
+            inner: Spooled::Memory(Vec::new()),
+        }
+    }
+
+    /// Returns `true` if the document has been moved to a temporary file.
+    #[must_use]
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.inner, Spooled::File(_))
+    }
+
+    /// Returns the document, rewinding the temporary file if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temporary file cannot be flushed or rewound.
+    pub fn into_inner(self) -> Result<Spooled> {
+        match self.inner {
+            Spooled::Memory(buf) => Ok(Spooled::Memory(buf)),
+            Spooled::File(mut file) => {
+                file.flush()?;
+                file.seek(SeekFrom::Start(0))?;
+                Ok(Spooled::File(file))
+            }
+        }
+    }
+}
+
+impl Write for SpooledWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if let Spooled::Memory(memory) = &self.inner {
+            if memory.len() + buf.len() > self.threshold {
+                let mut file = tempfile::tempfile()?;
+                file.write_all(memory)?;
+                self.inner = Spooled::File(file);
+            }
+        }
+        match &mut self.inner {
+            Spooled::Memory(memory) => memory.write(buf),
+            Spooled::File(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match &mut self.inner {
+            Spooled::Memory(_) => Ok(()),
+            Spooled::File(file) => file.flush(),
+        }
+    }
+}
+
+/// A [`PartSource`] for a body that is generated up front into a [`SpooledWriter`], so that its
+/// length is known before it is written.
+///
+/// Bodies that are produced as they are written, such as by a closure, have no length until
+/// they are done. When the server needs a `Content-Length`, staging the body first lets
+/// [`FormData::content_length`][crate::FormData::content_length] count it, without holding a
+/// large body in memory.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{FormData, StagedSource};
+/// use std::io::Write;
+///
+/// let report = StagedSource::new(1024 * 1024, |out| {
+///     for row in 0..1000 {
+///         writeln!(out, "{row},{}", row * row)?;
+///     }
+///     Ok(())
+/// })?
+/// .with_filename("report.csv")
+/// .with_content_type("text/csv");
+///
+/// let mut form = FormData::new(Vec::new());
+/// let content_length = form.content_length([("report", &report)]);
+/// form.write_source("report", report)?;
+/// assert_eq!(content_length, Some(form.finish()?.len() as u64));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StagedSource {
+    body: Spooled,
+    filename: Option<OsString>,
+    content_type: Option<String>,
+}
+
+impl StagedSource {
+    /// Runs `generate` to write the body to a [`SpooledWriter`] that moves to a temporary file
+    /// once more than `threshold` bytes are written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error from `generate`, or if the temporary file can't be created or rewound.
+    pub fn new<F>(threshold: usize, generate: F) -> Result<StagedSource>
+    where
+        F: FnOnce(&mut SpooledWriter) -> Result<()>,
+    {
+        let mut writer = SpooledWriter::new(threshold);
+        generate(&mut writer)?;
+        Ok(StagedSource {
+            body: writer.into_inner()?,
+            filename: None,
+            content_type: None,
+        })
+    }
+
+    /// Sets the `filename` parameter.
+    #[must_use]
+    pub fn with_filename<F: Into<OsString>>(mut self, filename: F) -> StagedSource {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Sets the `Content-Type`.
+    #[must_use]
+    pub fn with_content_type<C: Into<String>>(mut self, content_type: C) -> StagedSource {
+        self.content_type = Some(content_type.into());
+        self
+    }
+}
+
+impl PartSource for StagedSource {
+    type Reader<'a> = Box<dyn Read + 'a>;
+
+    fn reader(&mut self) -> Result<Box<dyn Read + '_>> {
+        match &mut self.body {
+            Spooled::Memory(body) => Ok(Box::new(&body[..])),
+            Spooled::File(file) => {
+                file.seek(SeekFrom::Start(0))?;
+                Ok(Box::new(&*file))
+            }
+        }
+    }
+
+    fn len_hint(&self) -> Option<u64> {
+        match &self.body {
+            Spooled::Memory(body) => Some(body.len() as u64),
+            Spooled::File(file) => Some(file.metadata().ok()?.len()),
+        }
+    }
+
+    fn filename(&self) -> Option<&OsStr> {
+        self.filename.as_deref()
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Spooled, SpooledWriter};
+    use crate::FormData;
+    use std::io::Read;
+
+    #[test]
+    fn spill() {
+        for (threshold, spilled) in [(1 << 20, false), (64, true)] {
+            let mut form = FormData::new(SpooledWriter::new(threshold));
+            form.write_path("corro", "testdata/corro.svg", "image/svg+xml")
+                .unwrap();
+            let writer = form.finish().unwrap();
+            assert_eq!(writer.is_spilled(), spilled);
+
+            let document = match writer.into_inner().unwrap() {
+                Spooled::Memory(document) => document,
+                Spooled::File(mut file) => {
+                    let mut document = Vec::new();
+                    file.read_to_end(&mut document).unwrap();
+                    document
+                }
+            };
+            assert!(document.len() > 7576);
+        }
+    }
+
+    #[test]
+    fn staged() {
+        use super::StagedSource;
+        use std::io::Write;
+
+        let mut documents = Vec::new();
+        for threshold in [1 << 20, 16] {
+            let source = StagedSource::new(threshold, |out| {
+                out.write_all(include_bytes!("../testdata/corro.svg"))
+            })
+            .unwrap()
+            .with_filename("corro.svg");
+            assert_eq!(matches!(source.body, Spooled::File(_)), threshold == 16);
+            let mut form = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+            let content_length = form.content_length([("corro", &source)]);
+            form.write_source("corro", source).unwrap();
+            let document = form.finish().unwrap();
+            assert_eq!(content_length, Some(document.len() as u64));
+            documents.push(document);
+        }
+        assert_eq!(documents[0], documents[1]);
+
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected
+            .write_path("corro", "testdata/corro.svg", "image/svg+xml")
+            .unwrap();
+        assert_eq!(documents[0], expected.finish().unwrap());
+    }
+}
@@ -0,0 +1,39 @@
+use std::io::{Result, Write};
+
+/// A writer that writes everything to two writers. See [`FormData::tee`][crate::FormData::tee].
+#[derive(Debug, Clone)]
+pub struct Tee<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Tee<A, B> {
+    /// Creates a writer that writes to both `first` and `second`.
+    pub fn new(first: A, second: B) -> Tee<A, B> {
+        Tee { first, second }
+    }
+
+    /// The second writer.
+    #[cfg(feature = "hmac")]
+    pub(crate) fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// Returns the two writers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.first.write(buf)?;
+        self.second.write_all(&buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
@@ -0,0 +1,957 @@
+use crate::{FormData, Part, Reserve, Truncate};
+use std::borrow::Cow;
+use std::fs::Metadata;
+use std::io::{Error, ErrorKind, Read, Result, Seek, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A reusable list of [`Part`]s that can be written as a document any number of times.
+///
+/// Parts read from files (see [`Part::path`]) are reopened each time the template is emitted, so
+/// an HTTP client can retry an idempotent upload after a transient failure by emitting the same
+/// template again. Since nothing is opened until then, a template can be built early (for
+/// example, while loading configuration) and emitted much later.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use form_data_builder::{FormData, FormDataTemplate, Part};
+///
+/// let mut template = FormDataTemplate::new();
+/// template.push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+/// template.push(Part::text("cute", "yes"));
+///
+/// for _attempt in 0..3 {
+///     let form = FormData::new(Vec::new());
+///     let content_type = form.content_type_header();
+///     let body = template.emit(form)?;
+///     // ... send `body` with `content_type`, and stop if it succeeds ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormDataTemplate<'a> {
+    parts: Vec<Part<'a>>,
+    replay: ReplayCache,
+    skip_empty: bool,
+}
+
+/// Bodies of small files read by earlier emissions of a template, by part index. See
+/// [`FormDataTemplate::set_replay_limit`].
+#[derive(Debug, Default)]
+struct ReplayCache {
+    limit: u64,
+    bodies: Mutex<Vec<Option<Arc<Replayed>>>>,
+}
+
+/// A file's body and metadata, as read for a [`ReplayCache`].
+#[derive(Debug)]
+struct Replayed {
+    body: Vec<u8>,
+    metadata: Metadata,
+}
+
+impl ReplayCache {
+    fn get(&self, index: usize) -> Option<Arc<Replayed>> {
+        let bodies = self.bodies.lock().unwrap_or_else(PoisonError::into_inner);
+        bodies.get(index).cloned().flatten()
+    }
+
+    fn insert(&self, index: usize, replayed: Arc<Replayed>) {
+        let mut bodies = self.bodies.lock().unwrap_or_else(PoisonError::into_inner);
+        if bodies.len() <= index {
+            bodies.resize(index + 1, None);
+        }
+        bodies[index] = Some(replayed);
+    }
+
+    fn forget(&mut self, index: usize) {
+        let bodies = self
+            .bodies
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(body) = bodies.get_mut(index) {
+            *body = None;
+        }
+    }
+
+    /// Moves cached bodies along with their parts, which are now in `order`.
+    fn permute(&mut self, order: &[usize]) {
+        let bodies = self
+            .bodies
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !bodies.is_empty() {
+            *bodies = order
+                .iter()
+                .map(|&index| bodies.get(index).cloned().flatten())
+                .collect();
+        }
+    }
+}
+
+impl Clone for ReplayCache {
+    fn clone(&self) -> ReplayCache {
+        let bodies = self.bodies.lock().unwrap_or_else(PoisonError::into_inner);
+        ReplayCache {
+            limit: self.limit,
+            bodies: Mutex::new(bodies.clone()),
+        }
+    }
+}
+
+/// Cached bodies are only copies of the files, so they don't make templates different.
+impl PartialEq for ReplayCache {
+    fn eq(&self, other: &ReplayCache) -> bool {
+        self.limit == other.limit
+    }
+}
+
+impl Eq for ReplayCache {}
+
+impl<'a> FormDataTemplate<'a> {
+    /// Creates an empty template.
+    #[must_use]
+    pub fn new() -> FormDataTemplate<'a> {
+        FormDataTemplate::default()
+    }
+
+    /// Adds a part to the end of the template.
+    ///
+    /// Parts can borrow their data, as long as the template doesn't outlive it. A template built
+    /// from `'static` parts (see [`Part::into_owned`]) can be kept indefinitely.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, FormDataTemplate, Part};
+    /// # use std::path::Path;
+    /// fn respond(user: &str, avatar: &[u8], svg: &Path) -> std::io::Result<Vec<u8>> {
+    ///     let mut template = FormDataTemplate::new();
+    ///     template
+    ///         .push(Part::text("user", user))
+    ///         .push(Part::file("avatar", avatar, "image/png"))
+    ///         .push(Part::borrowed_path("corro", svg, "image/svg+xml"));
+    ///     template.emit(FormData::new(Vec::new()))
+    /// }
+    /// # respond("ferris", b"\x89PNG", Path::new("testdata/corro.svg"))?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn push(&mut self, part: Part<'a>) -> &mut FormDataTemplate<'a> {
+        self.parts.push(part);
+        self
+    }
+
+    /// Moves the parts called `name` to the front of the template, keeping them in the same order
+    /// as each other, for services that want some fields (such as metadata) before the rest.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormDataTemplate, Part};
+    /// let mut template: FormDataTemplate = [
+    ///     Part::path("corro", "testdata/corro.svg", "image/svg+xml"),
+    ///     Part::text("metadata", "{}"),
+    /// ]
+    /// .into_iter()
+    /// .collect();
+    /// template.move_to_front("metadata");
+    /// assert_eq!(template.parts()[0].name(), "metadata");
+    /// ```
+    pub fn move_to_front(&mut self, name: &str) -> &mut FormDataTemplate<'a> {
+        let (mut order, rest): (Vec<usize>, Vec<usize>) =
+            (0..self.parts.len()).partition(|&index| self.parts[index].name() == name);
+        order.extend(rest);
+        self.permute(&order);
+        self
+    }
+
+    /// Sorts the parts by name. Parts with the same name stay in the same order as each other.
+    pub fn sort_by_name(&mut self) -> &mut FormDataTemplate<'a> {
+        let mut order: Vec<usize> = (0..self.parts.len()).collect();
+        order.sort_by_key(|&index| self.parts[index].name());
+        self.permute(&order);
+        self
+    }
+
+    /// Puts the parts in `order`, which lists each part's current index once, in the order
+    /// they should be written.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormDataTemplate, Part};
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut template: FormDataTemplate = ["a", "b", "c"]
+    ///     .into_iter()
+    ///     .map(|name| Part::text(name, "yes"))
+    ///     .collect();
+    /// template.reorder(&[2, 0, 1])?;
+    /// let names: Vec<_> = template.parts().iter().map(|part| part.name()).collect();
+    /// assert_eq!(names, ["c", "a", "b"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidInput`][std::io::ErrorKind::InvalidInput],
+    /// and leaves the template as it was, if `order` doesn't list each index exactly once.
+    pub fn reorder(&mut self, order: &[usize]) -> Result<&mut FormDataTemplate<'a>> {
+        let mut seen = vec![false; self.parts.len()];
+        let valid = order.len() == self.parts.len()
+            && order
+                .iter()
+                .all(|&index| index < seen.len() && !std::mem::replace(&mut seen[index], true));
+        if !valid {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{order:?} is not an order for {} parts", self.parts.len()),
+            ));
+        }
+        self.permute(order);
+        Ok(self)
+    }
+
+    /// Removes the parts called `name`, for customizing a long-lived template for one request.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormData, FormDataTemplate, Part};
+    /// # fn main() -> std::io::Result<()> {
+    /// let mut base = FormDataTemplate::new();
+    /// base.push(Part::text("api_version", "2"))
+    ///     .push(Part::text("debug", "true"));
+    ///
+    /// let mut template = base.clone();
+    /// template
+    ///     .remove("debug")
+    ///     .replace("api_version", Part::text("api_version", "3"))
+    ///     .push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+    /// let body = template.emit(FormData::new(Vec::new()))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove(&mut self, name: &str) -> &mut FormDataTemplate<'a> {
+        let order: Vec<usize> = (0..self.parts.len())
+            .filter(|&index| self.parts[index].name() != name)
+            .collect();
+        self.permute(&order);
+        self
+    }
+
+    /// Puts `part` where the first part called `name` is, and removes any others called `name`.
+    /// If there are none, `part` is added to the end of the template. See
+    /// [`FormDataTemplate::remove`].
+    pub fn replace(&mut self, name: &str, part: Part<'a>) -> &mut FormDataTemplate<'a> {
+        let Some(first) = self.parts.iter().position(|part| part.name() == name) else {
+            return self.push(part);
+        };
+        self.remove_after(first, name);
+        self.parts[first] = part;
+        self.replay.forget(first);
+        self
+    }
+
+    /// Removes the parts called `name` after the one at `index`.
+    fn remove_after(&mut self, index: usize, name: &str) {
+        let order: Vec<usize> = (0..self.parts.len())
+            .filter(|&i| i <= index || self.parts[i].name() != name)
+            .collect();
+        self.permute(&order);
+    }
+
+    /// Puts the parts, and anything cached for them, in `order`, leaving out any parts it
+    /// doesn't list. It must list each index at most once.
+    fn permute(&mut self, order: &[usize]) {
+        let mut parts: Vec<Option<Part<'a>>> = self.parts.drain(..).map(Some).collect();
+        self.parts = order
+            .iter()
+            .map(|&index| parts[index].take().expect("each index is listed once"))
+            .collect();
+        self.replay.permute(order);
+    }
+
+    /// The parts of the template, in order.
+    #[must_use]
+    pub fn parts(&self) -> &[Part<'a>] {
+        &self.parts
+    }
+
+    /// Keeps the bodies of files of up to `limit` bytes in memory the first time they are
+    /// written, and writes the kept copies from then on, so that retrying an upload doesn't read
+    /// small files again. Larger files are still reopened each time. A limit of 0, the default,
+    /// keeps nothing.
+    ///
+    /// [`FormDataTemplate::emit`] and [`FormDataTemplate::resume`] use the kept copies. Changes to
+    /// a file after its body is kept are not picked up.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use form_data_builder::{FormData, FormDataTemplate, Part};
+    ///
+    /// let mut template = FormDataTemplate::new();
+    /// template.set_replay_limit(64 * 1024);
+    /// template.push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+    /// for _attempt in 0..3 {
+    ///     // corro.svg is only read the first time
+    ///     let body = template.emit(FormData::new(Vec::new()))?;
+    ///     // ... send `body`, and stop if it succeeds ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_replay_limit(&mut self, limit: u64) -> &mut FormDataTemplate<'a> {
+        self.replay.limit = limit;
+        self
+    }
+
+    /// Leaves out non-file fields with empty values when the template is written, so that optional
+    /// fields can be declared once and filled in or left empty per request.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormDataTemplate, Part};
+    /// # fn comment() -> Option<String> { None }
+    /// let mut template = FormDataTemplate::new();
+    /// template
+    ///     .set_skip_empty(true)
+    ///     .push(Part::text("cute", "yes"))
+    ///     .push(Part::text("comment", comment().unwrap_or_default()));
+    /// assert_eq!(template.plan().count(), 1);
+    /// ```
+    pub fn set_skip_empty(&mut self, enabled: bool) -> &mut FormDataTemplate<'a> {
+        self.skip_empty = enabled;
+        self
+    }
+
+    /// The parts that are written, with their indices.
+    fn written(&self) -> impl Iterator<Item = (usize, &Part<'a>)> + '_ {
+        self.parts.iter().enumerate().filter(|(_, part)| {
+            !(self.skip_empty && part.is_text() && part.body().is_some_and(<[u8]>::is_empty))
+        })
+    }
+
+    /// Lists the parts that would be written, with how big each one's body is as of now, so that
+    /// they can be logged, checked against what an API expects, or shown to a user for
+    /// confirmation before anything is written.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormDataTemplate, Part};
+    /// let mut template = FormDataTemplate::new();
+    /// template.push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+    /// template.push(Part::text("cute", "yes"));
+    ///
+    /// for planned in template.plan() {
+    ///     match planned.size {
+    ///         Some(size) => println!("{}: {size} bytes", planned.part.name()),
+    ///         None => println!("{}: missing", planned.part.name()),
+    ///     }
+    /// }
+    /// let total: Option<u64> = template.plan().map(|planned| planned.size).sum();
+    /// assert_eq!(total, Some(7576 + 3));
+    /// ```
+    pub fn plan(&self) -> impl Iterator<Item = PlannedPart<'_, 'a>> + '_ {
+        self.written().map(|(_, part)| PlannedPart {
+            part,
+            size: match (part.body(), part.file_path()) {
+                (Some(body), _) => Some(body.len() as u64),
+                (None, Some(path)) => std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+                (None, None) => Some(0),
+            },
+        })
+    }
+
+    /// Renders a `curl` command that posts the same parts to `url`, for reproducing a request
+    /// outside of Rust.
+    ///
+    /// The values of secret fields (see [`Part::secret`]) are replaced with `[REDACTED]`. Files are
+    /// passed with `@`, and parts whose bodies are held in memory are read from a file named after
+    /// the part's filename, or `<name>.bin`, which has to be created before running the command.
+    /// Such a part without a filename is sent with an empty one, since curl always sends a
+    /// filename for files. Headers that curl writes itself, such as
+    /// [`Part::with_modification_date`], are left out.
+    ///
+    /// ```
+    /// # use form_data_builder::{FormDataTemplate, Part};
+    /// let mut template = FormDataTemplate::new();
+    /// template
+    ///     .push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"))
+    ///     .push(Part::text("cute", "yes"))
+    ///     .push(Part::secret("token", "hunter2"));
+    /// assert_eq!(
+    ///     template.to_curl_command("https://example.com/upload")?,
+    ///     "curl 'https://example.com/upload' \\\n  \
+    ///        --form 'corro=@testdata/corro.svg;filename=corro.svg;type=image/svg+xml' \\\n  \
+    ///        --form-string 'cute=yes' \\\n  \
+    ///        --form-string 'token=[REDACTED]'",
+    /// );
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind [`ErrorKind::InvalidInput`] if a part's name contains `=`, which
+    /// curl would take as the end of the name.
+    pub fn to_curl_command(&self, url: &str) -> Result<String> {
+        let mut command = format!("curl {}", shell_quote(url));
+        for (_, part) in self.written() {
+            if part.name().contains('=') {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "curl can't send part name {:?}, which contains `=`",
+                        part.name()
+                    ),
+                ));
+            }
+            command.push_str(" \\\n  ");
+            if part.is_text() {
+                let value = if part.is_sensitive() {
+                    "[REDACTED]".into()
+                } else {
+                    String::from_utf8_lossy(part.body().unwrap_or_default())
+                };
+                command.push_str("--form-string ");
+                command.push_str(&shell_quote(&format!("{}={value}", part.name())));
+                continue;
+            }
+
+            let path = match part.file_path() {
+                Some(path) => path.to_string_lossy(),
+                None => part
+                    .filename()
+                    .map_or_else(|| format!("{}.bin", part.name()).into(), Into::into),
+            };
+            let mut arg = format!("{}=@{}", part.name(), curl_quote(&path));
+            let filename = part.filename().map(Into::into).or_else(|| {
+                part.file_path()
+                    .and_then(|path| path.file_name())
+                    .map(|filename| filename.to_string_lossy())
+            });
+            arg.push_str(";filename=");
+            arg.push_str(&curl_quote(&filename.unwrap_or_default()));
+            if let Some(content_type) = part.content_type() {
+                arg.push_str(";type=");
+                arg.push_str(&curl_quote(content_type));
+            }
+            command.push_str("--form ");
+            command.push_str(&shell_quote(&arg));
+        }
+        Ok(command)
+    }
+
+    /// Writes every part to `form` and finishes it, returning the writer.
+    ///
+    /// Taking a [`FormData`] rather than a writer lets the caller read its
+    /// [`content_type_header`][FormData::content_type_header] and configure it first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a part's file cannot be opened or if the writer fails. Errors opening a
+    /// file name the part they belong to.
+    pub fn emit<W: Write>(&self, mut form: FormData<W>) -> Result<W> {
+        let result = self
+            .written()
+            .try_for_each(|(index, _)| self.write_part(&mut form, index));
+        form.finish_or_abort(result)
+    }
+
+    /// Writes the part at `index` to `form`, from the replay cache if it can.
+    fn write_part<W: Write>(&self, form: &mut FormData<W>, index: usize) -> Result<()> {
+        let part = &self.parts[index];
+        let Some(path) = part.file_path().filter(|_| self.replay.limit > 0) else {
+            return form.write_part(part);
+        };
+        let replayed = if let Some(replayed) = self.replay.get(index) {
+            replayed
+        } else {
+            let Some(file) = form.open_path(part.name(), path)? else {
+                return Ok(());
+            };
+            let metadata = file.metadata()?;
+            if metadata.len() > self.replay.limit {
+                // too big to keep; write it as usual without reading it twice
+                return form.write_part(part);
+            }
+            let mut body = Vec::new();
+            let read = file.take(self.replay.limit + 1).read_to_end(&mut body);
+            if read.is_err() || body.len() as u64 > self.replay.limit {
+                // failing, or grown since; write it as usual
+                return form.write_part(part);
+            }
+            let replayed = Arc::new(Replayed { body, metadata });
+            self.replay.insert(index, replayed.clone());
+            replayed
+        };
+        let metadata = form.options().file_metadata.then_some(&replayed.metadata);
+        // bound so that the borrowing part is dropped before `replayed`
+        let result = form.write_part(&part.replayed(&replayed.body, metadata));
+        result
+    }
+
+    /// Like [`FormDataTemplate::emit`], but first reserves room in an in-memory writer for the
+    /// whole document with [`FormData::reserve`], so that large documents aren't copied each time
+    /// the writer grows.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use form_data_builder::{FormData, FormDataTemplate, Part};
+    ///
+    /// let mut template = FormDataTemplate::new();
+    /// template.push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+    /// let body = template.emit_reserved(FormData::new(Vec::new()))?;
+    /// assert_eq!(body.capacity(), body.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`FormDataTemplate::emit`].
+    pub fn emit_reserved<W: Write + Reserve>(&self, mut form: FormData<W>) -> Result<W> {
+        form.reserve_for(self.written().map(|(_, part)| part));
+        self.emit(form)
+    }
+
+    /// Writes the parts of the template that `form` doesn't have yet and finishes it, returning
+    /// the writer.
+    ///
+    /// `form` should only have been written to by this method. It is first rewound with
+    /// [`FormData::resume`], so a part that failed partway through is written again from its
+    /// start. Unlike [`FormDataTemplate::emit`], `form` is left as it is on error so that it can
+    /// be resumed again; call [`FormData::abort`] to give up on it instead.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use form_data_builder::{FormData, FormDataTemplate, Part};
+    ///
+    /// let mut template = FormDataTemplate::new();
+    /// template.push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+    /// template.push(Part::text("cute", "yes"));
+    ///
+    /// // or a staging file
+    /// let mut form = FormData::new(std::io::Cursor::new(Vec::new()));
+    /// let staged = loop {
+    ///     match template.resume(&mut form) {
+    ///         Ok(staged) => break staged,
+    ///         // ... give up with `form.abort()` after too many attempts ...
+    ///         Err(err) => eprintln!("retrying: {err}"),
+    ///     }
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `form` can't be rewound, or as from [`FormDataTemplate::emit`].
+    pub fn resume<W: Write + Seek + Truncate>(&self, form: &mut FormData<W>) -> Result<W> {
+        form.resume()?;
+        let done = form.last_progress().parts;
+        for (index, _) in self.written().skip(done) {
+            self.write_part(form, index)?;
+        }
+        form.finish()
+    }
+
+    /// Like [`FormDataTemplate::emit`], but first passes each part through `preprocess` on the
+    /// [rayon] thread pool.
+    ///
+    /// This is for CPU-bound transforms such as compressing or hashing part bodies. Parts are
+    /// preprocessed in parallel and written in order as soon as each is ready, so the transforms
+    /// don't wait behind writes to a slow writer. Preprocessed parts that are ready early are held
+    /// in memory until their turn. A part that no thread has started on by its turn is
+    /// preprocessed by the calling thread, so this can be called from inside the pool, even one
+    /// with a single thread. Parts that `preprocess` returns unchanged are written like
+    /// [`FormDataTemplate::emit`] writes them, from the replay cache if they can be.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use form_data_builder::{FormData, FormDataTemplate, Part};
+    ///
+    /// let template: FormDataTemplate = (0..8)
+    ///     .map(|i| Part::text(format!("field{i}"), "yes"))
+    ///     .collect();
+    /// let document = template.emit_parallel(FormData::new(Vec::new()), |part| {
+    ///     // ... compress or hash the part's body ...
+    ///     Ok(part.clone())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [rayon]: https://docs.rs/rayon
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `preprocess` in template order, or an error writing a part
+    /// as from [`FormDataTemplate::emit`]. Parts after the error are not written.
+    #[cfg(feature = "rayon")]
+    pub fn emit_parallel<W, F>(&self, mut form: FormData<W>, preprocess: F) -> Result<W>
+    where
+        W: Write,
+        F: for<'p> Fn(&'p Part<'a>) -> Result<Part<'p>> + Sync,
+    {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::mpsc;
+
+        let written: Vec<_> = self.written().collect();
+        // whether a thread has started preprocessing each part
+        let started: Vec<_> = written.iter().map(|_| AtomicBool::new(false)).collect();
+        rayon::in_place_scope(|scope| {
+            let preprocess = &preprocess;
+            let pending: Vec<_> = written
+                .iter()
+                .zip(&started)
+                .map(|(&(_, part), started)| {
+                    let (tx, rx) = mpsc::sync_channel(1);
+                    scope.spawn(move |_| {
+                        if !started.swap(true, Ordering::AcqRel) {
+                            let _ = tx.send(preprocess(part));
+                        }
+                    });
+                    rx
+                })
+                .collect();
+            let result = written.iter().zip(&started).zip(pending).try_for_each(
+                |((&(index, part), started), rx)| {
+                    let processed = if started.swap(true, Ordering::AcqRel) {
+                        // another thread is working on it, so waiting can't block the pool
+                        rx.recv().map_err(std::io::Error::other)??
+                    } else {
+                        preprocess(part)?
+                    };
+                    if part.file_path().is_some() && processed == *part {
+                        self.write_part(&mut form, index)
+                    } else {
+                        form.write_part(&processed)
+                    }
+                },
+            );
+            // after an error, spare the parts no thread has started on
+            for started in &started {
+                started.store(true, Ordering::Release);
+            }
+            form.finish_or_abort(result)
+        })
+    }
+}
+
+/// Quotes `s` as a single POSIX shell word.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Quotes `s` for a `curl --form` value if it contains characters curl would split on.
+fn curl_quote(s: &str) -> Cow<'_, str> {
+    if s.contains([';', ',', '"', '\\']) {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")).into()
+    } else {
+        s.into()
+    }
+}
+
+/// A part of a [`FormDataTemplate`], from [`FormDataTemplate::plan`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct PlannedPart<'t, 'a> {
+    /// The part, with its name and where its body comes from.
+    pub part: &'t Part<'a>,
+    /// The size of the part's body, or `None` if it is read from a file that can't be found.
+    ///
+    /// A file's size is its size when [`FormDataTemplate::plan`] was called, and may change by
+    /// the time it is written. Options like
+    /// [`FormData::set_normalize_newlines`][crate::FormData::set_normalize_newlines] can also
+    /// change the size of what's written.
+    pub size: Option<u64>,
+}
+
+impl<'a> FromIterator<Part<'a>> for FormDataTemplate<'a> {
+    fn from_iter<I: IntoIterator<Item = Part<'a>>>(iter: I) -> Self {
+        FormDataTemplate {
+            parts: iter.into_iter().collect(),
+            replay: ReplayCache::default(),
+            skip_empty: false,
+        }
+    }
+}
+
+impl<'a> Extend<Part<'a>> for FormDataTemplate<'a> {
+    fn extend<I: IntoIterator<Item = Part<'a>>>(&mut self, iter: I) {
+        self.parts.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FormData, FormDataTemplate, Part};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn replay() {
+        let path = std::env::temp_dir().join(format!("replay-{}.txt", std::process::id()));
+        std::fs::write(&path, "yes").unwrap();
+        let mut template = FormDataTemplate::new();
+        template
+            .push(Part::path("cute", &path, "text/plain"))
+            .push(Part::path("corro", "testdata/corro.svg", "image/svg+xml"));
+        template.set_replay_limit(100);
+
+        let emit = |template: &FormDataTemplate<'_>| {
+            template
+                .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+                .unwrap()
+        };
+        let first = emit(&template);
+        std::fs::write(&path, "no").unwrap();
+        assert_eq!(emit(&template), first);
+        // clones keep what was cached
+        assert_eq!(emit(&template.clone()), first);
+        template.set_replay_limit(0);
+        assert_ne!(emit(&template), first);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reorder() {
+        let path = std::env::temp_dir().join(format!("reorder-{}.txt", std::process::id()));
+        std::fs::write(&path, "yes").unwrap();
+        let mut template = FormDataTemplate::new();
+        template
+            .push(Part::text("b", "1"))
+            .push(Part::path("cute", &path, "text/plain"))
+            .push(Part::text("a", "2"))
+            .push(Part::text("b", "3"));
+        template.set_replay_limit(100);
+        let emit = |template: &FormDataTemplate<'_>| {
+            template
+                .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+                .unwrap()
+        };
+        emit(&template);
+        std::fs::write(&path, "no").unwrap();
+
+        let names = |template: &FormDataTemplate<'_>| {
+            template
+                .parts()
+                .iter()
+                .map(|part| part.name().to_owned())
+                .collect::<Vec<_>>()
+        };
+        template.sort_by_name();
+        assert_eq!(names(&template), ["a", "b", "b", "cute"]);
+        assert_eq!(template.parts()[1].body(), Some(&b"1"[..]));
+        template.move_to_front("cute");
+        assert_eq!(names(&template), ["cute", "a", "b", "b"]);
+        assert!(template.reorder(&[0, 1, 2]).is_err());
+        assert!(template.reorder(&[0, 1, 1, 2]).is_err());
+        template.reorder(&[1, 0, 2, 3]).unwrap();
+        assert_eq!(names(&template), ["a", "cute", "b", "b"]);
+
+        // the kept copy of the file moves with its part
+        let document = String::from_utf8(emit(&template)).unwrap();
+        assert!(document.contains("\r\n\r\nyes\r\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn remove_and_replace() {
+        let path = std::env::temp_dir().join(format!("replace-{}.txt", std::process::id()));
+        std::fs::write(&path, "yes").unwrap();
+        let mut template = FormDataTemplate::new();
+        template
+            .push(Part::text("tag", "crab"))
+            .push(Part::path("cute", &path, "text/plain"))
+            .push(Part::text("tag", "unsafe"))
+            .push(Part::path("corro", &path, "text/plain"));
+        template.set_replay_limit(100);
+        template
+            .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+            .unwrap();
+        std::fs::write(&path, "no").unwrap();
+
+        template
+            .remove("tag")
+            .replace("cute", Part::text("cute", "very"))
+            .replace("missing", Part::text("missing", "found"));
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected.write_field("cute", "very").unwrap();
+        // corro's kept copy is still used after moving
+        expected
+            .write_file(
+                "corro",
+                &b"yes"[..],
+                Some(path.file_name().unwrap()),
+                "text/plain",
+            )
+            .unwrap();
+        expected.write_field("missing", "found").unwrap();
+        assert_eq!(
+            template
+                .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+                .unwrap(),
+            expected.finish().unwrap()
+        );
+
+        template.replace("cute", Part::path("cute", &path, "text/plain"));
+        let document = template
+            .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+            .unwrap();
+        // the replaced part is read afresh
+        assert!(String::from_utf8(document)
+            .unwrap()
+            .contains("\r\n\r\nno\r\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn skip_empty() {
+        let mut template = FormDataTemplate::new();
+        template
+            .push(Part::text("comment", ""))
+            .push(Part::file("empty", &b""[..], "text/plain"))
+            .push(Part::text("cute", "yes"))
+            .set_skip_empty(true);
+        let mut expected = FormData::with_boundary(Vec::new(), "boundary").unwrap();
+        expected
+            .write_file("empty", &b""[..], None, "text/plain")
+            .unwrap();
+        expected.write_field("cute", "yes").unwrap();
+        let expected = expected.finish().unwrap();
+        assert_eq!(
+            template
+                .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+                .unwrap(),
+            expected
+        );
+
+        // resuming counts only the parts that are written
+        let mut form =
+            FormData::with_boundary(std::io::Cursor::new(Vec::new()), "boundary").unwrap();
+        form.write_file("empty", &b""[..], None, "text/plain")
+            .unwrap();
+        let document = template.resume(&mut form).unwrap().into_inner();
+        assert_eq!(document, expected);
+
+        // and so does reserving room for them
+        let document = template
+            .emit_reserved(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+            .unwrap();
+        assert_eq!(document.capacity(), expected.len());
+    }
+
+    #[test]
+    fn to_curl_command() {
+        let mut template = FormDataTemplate::new();
+        template
+            .push(Part::text("it's", "a;b"))
+            .push(Part::file("data", &b"\0"[..], "application/octet-stream"))
+            .push(Part::borrowed_path(
+                "odd",
+                "dir/a;b \"c\".txt".as_ref(),
+                "text/plain",
+            ));
+        assert_eq!(
+            template
+                .to_curl_command("https://example.com/?a=1&b=2")
+                .unwrap(),
+            "curl 'https://example.com/?a=1&b=2' \\\n  \
+               --form-string 'it'\\''s=a;b' \\\n  \
+               --form 'data=@data.bin;filename=;type=application/octet-stream' \\\n  \
+               --form 'odd=@\"dir/a;b \\\"c\\\".txt\";\
+               filename=\"a;b \\\"c\\\".txt\";type=text/plain'"
+        );
+
+        let mut template = FormDataTemplate::new();
+        template.push(
+            Part::file("report", &b"{}"[..], "application/json")
+                .with_content_type_param("profile", "https://example.com/report; v2")
+                .unwrap(),
+        );
+        assert_eq!(
+            template.to_curl_command("https://example.com/").unwrap(),
+            "curl 'https://example.com/' \\\n  \
+               --form 'report=@report.bin;filename=;\
+               type=\"application/json; profile=\\\"https://example.com/report; v2\\\"\"'"
+        );
+
+        template.push(Part::text("a=b", "c"));
+        let err = template
+            .to_curl_command("https://example.com/")
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn emit_parallel() {
+        use crate::FormData;
+        use std::io::{Error, ErrorKind};
+
+        let template: FormDataTemplate = (0..64)
+            .map(|i| Part::text(format!("field{i}"), i.to_string()))
+            .collect();
+        let expected: FormDataTemplate = (0..64)
+            .map(|i| Part::text(format!("field{i}"), (i * 2).to_string()))
+            .collect();
+        let document = template
+            .emit_parallel(
+                FormData::with_boundary(Vec::new(), "boundary").unwrap(),
+                |part| {
+                    let value: u32 = std::str::from_utf8(part.body().unwrap())
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    Ok(Part::text(part.name(), (value * 2).to_string()))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            document,
+            expected
+                .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+                .unwrap()
+        );
+
+        let err = template
+            .emit_parallel(FormData::new(Vec::new()), |part| match part.name() {
+                "field7" => Err(Error::new(ErrorKind::InvalidData, "nope")),
+                _ => Ok(part.clone()),
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // the only worker thread is the one emitting
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let single = pool.install(|| {
+            template.emit_parallel(
+                FormData::with_boundary(Vec::new(), "boundary").unwrap(),
+                |part| Ok(part.clone()),
+            )
+        });
+        assert_eq!(
+            single.unwrap(),
+            template
+                .emit(FormData::with_boundary(Vec::new(), "boundary").unwrap())
+                .unwrap()
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn emit_parallel_replay() {
+        let path = std::env::temp_dir().join(format!("parallel-{}.txt", std::process::id()));
+        std::fs::write(&path, "yes").unwrap();
+        let mut template = FormDataTemplate::new();
+        template.push(Part::path("cute", &path, "text/plain"));
+        template.set_replay_limit(100);
+
+        let emit = |template: &FormDataTemplate<'_>| {
+            template
+                .emit_parallel(
+                    FormData::with_boundary(Vec::new(), "boundary").unwrap(),
+                    |part| Ok(part.clone()),
+                )
+                .unwrap()
+        };
+        let first = emit(&template);
+        std::fs::write(&path, "no").unwrap();
+        assert_eq!(emit(&template), first);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
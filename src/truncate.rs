@@ -0,0 +1,40 @@
+use std::fs::File;
+use std::io::{Cursor, Result};
+
+/// A writer whose contents can be cut short, for [`FormData::resume`][crate::FormData::resume].
+pub trait Truncate {
+    /// Discards everything after the first `len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the contents cannot be truncated.
+    fn truncate(&mut self, len: u64) -> Result<()>;
+}
+
+impl Truncate for File {
+    fn truncate(&mut self, len: u64) -> Result<()> {
+        self.set_len(len)
+    }
+}
+
+impl Truncate for &File {
+    fn truncate(&mut self, len: u64) -> Result<()> {
+        self.set_len(len)
+    }
+}
+
+impl Truncate for Cursor<Vec<u8>> {
+    fn truncate(&mut self, len: u64) -> Result<()> {
+        self.get_mut()
+            .truncate(usize::try_from(len).unwrap_or(usize::MAX));
+        Ok(())
+    }
+}
+
+impl Truncate for Cursor<&mut Vec<u8>> {
+    fn truncate(&mut self, len: u64) -> Result<()> {
+        self.get_mut()
+            .truncate(usize::try_from(len).unwrap_or(usize::MAX));
+        Ok(())
+    }
+}